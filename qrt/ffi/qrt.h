@@ -0,0 +1,117 @@
+/* The C API for QRT, generated from src/qrt/ffi.rs by cbindgen (see cbindgen.toml). Don't edit it by hand! */
+
+#ifndef QRT_H
+#define QRT_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct QrtInterpreter QrtInterpreter;
+
+typedef struct QrtResult QrtResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates an interpreter, to be freed with qrt_interpreter_free
+ */
+QrtInterpreter *qrt_interpreter_new(void);
+
+/**
+ * Frees an interpreter, doing nothing given NULL
+ *
+ * # Safety
+ * The interpreter must be NULL or one given by qrt_interpreter_new, which hasn't already been freed.
+ */
+void qrt_interpreter_free(QrtInterpreter *interpreter);
+
+/**
+ * Evaluates length bytes of source, with input written as a QRT literal (or NULL for a Void).
+ * Gives a result to be freed with qrt_result_free, which holds the error if the input literal was invalid or the
+ * interpreter was NULL.
+ *
+ * # Safety
+ * The interpreter must be NULL or one given by qrt_interpreter_new that hasn't been freed, and isn't being used by
+ * another thread. The source must be NULL or point to at least length bytes, and the input must be NULL or a
+ * NUL-terminated string.
+ */
+QrtResult *qrt_evaluate(QrtInterpreter *interpreter,
+                        const uint8_t *source,
+                        size_t length,
+                        const char *input);
+
+/**
+ * Whether the evaluation gave a value rather than throwing an error
+ *
+ * # Safety
+ * The result must be one given by qrt_evaluate, which hasn't been freed.
+ */
+bool qrt_result_ok(const QrtResult *result);
+
+/**
+ * The value as a QRT literal, to be freed with qrt_string_free.
+ * NULL if evaluation threw an error, or the value is a Gestalt holding a NUL byte.
+ *
+ * # Safety
+ * The result must be one given by qrt_evaluate, which hasn't been freed.
+ */
+char *qrt_result_literal(const QrtResult *result);
+
+/**
+ * The value as JSON, with pair Sets written as objects if asked to, to be freed with qrt_string_free.
+ * NULL if evaluation threw an error, or the value can't be written as JSON.
+ *
+ * # Safety
+ * The result must be one given by qrt_evaluate, which hasn't been freed.
+ */
+char *qrt_result_json(const QrtResult *result, bool objects);
+
+/**
+ * The error message, owned by the result (so freed along with it), or NULL if evaluation gave a value
+ *
+ * # Safety
+ * The result must be one given by qrt_evaluate, which hasn't been freed. The message can't be used once it has.
+ */
+const char *qrt_result_error(const QrtResult *result);
+
+/**
+ * The position in the source (counted in bytes) the error was thrown at, even within a jump, or 0 if evaluation gave a
+ * value
+ *
+ * # Safety
+ * The result must be one given by qrt_evaluate, which hasn't been freed.
+ */
+size_t qrt_result_error_position(const QrtResult *result);
+
+/**
+ * The line (counted from 0) the error was thrown on, or 0 if evaluation gave a value
+ *
+ * # Safety
+ * The result must be one given by qrt_evaluate, which hasn't been freed.
+ */
+size_t qrt_result_error_line(const QrtResult *result);
+
+/**
+ * Frees a result, doing nothing given NULL
+ *
+ * # Safety
+ * The result must be NULL or one given by qrt_evaluate, which hasn't already been freed.
+ */
+void qrt_result_free(QrtResult *result);
+
+/**
+ * Frees a string given by qrt_result_literal or qrt_result_json, doing nothing given NULL
+ *
+ * # Safety
+ * The string must be NULL or one given by qrt_result_literal or qrt_result_json, which hasn't already been freed.
+ */
+void qrt_string_free(char *string);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* QRT_H */
@@ -0,0 +1,79 @@
+/* Checks the C API from C, built and run by the ffi test in src/tests.rs. Prints what failed, and exits with 1 if anything did. */
+
+#include <stdio.h>
+#include <string.h>
+#include "qrt.h"
+
+static int failures = 0;
+
+static void check(int passed, const char *what) {
+    if (!passed) {
+        printf("failed: %s\n", what);
+        failures++;
+    }
+}
+
+static int equal(const char *given, const char *expected) {
+    return given != NULL && strcmp(given, expected) == 0;
+}
+
+int main(void) {
+    QrtInterpreter *interpreter = qrt_interpreter_new();
+
+    /* Values, as literals and as JSON */
+    const char *program = "[+$ {1}, \"two\", [[\"three\", _]]]";
+    QrtResult *result = qrt_evaluate(interpreter, (const uint8_t *)program, strlen(program), "41");
+    check(qrt_result_ok(result), "evaluation gives a value");
+    check(qrt_result_error(result) == NULL, "a value has no error");
+
+    char *literal = qrt_result_literal(result);
+    check(equal(literal, "[42, \"two\", [[\"three\", _]]]"), "values are given as literals");
+    qrt_string_free(literal);
+
+    char *json = qrt_result_json(result, false);
+    check(equal(json, "[42,\"two\",[[\"three\",null]]]"), "values are given as JSON");
+    qrt_string_free(json);
+
+    char *object = qrt_result_json(result, true);
+    check(equal(object, "[42,\"two\",{\"three\":null}]"), "pair Sets are given as JSON objects");
+    qrt_string_free(object);
+    qrt_result_free(result);
+
+    /* A NULL input is a Void */
+    result = qrt_evaluate(interpreter, (const uint8_t *)"Y${_}", 5, NULL);
+    literal = qrt_result_literal(result);
+    check(equal(literal, "\"Void\""), "a NULL input is a Void");
+    qrt_string_free(literal);
+    qrt_result_free(result);
+
+    /* Errors */
+    program = "#a{1}\n+(a){\"b\"}";
+    result = qrt_evaluate(interpreter, (const uint8_t *)program, strlen(program), NULL);
+    check(!qrt_result_ok(result), "errors are thrown");
+    check(qrt_result_literal(result) == NULL, "errors have no literal");
+    check(qrt_result_json(result, false) == NULL, "errors have no JSON");
+    check(equal(qrt_result_error(result), "Could not coerce Gestalt to Linear"), "errors have their message");
+    check(qrt_result_error_position(result) == 14, "errors have their position");
+    check(qrt_result_error_line(result) == 1, "errors have their line");
+    qrt_result_free(result);
+
+    /* Errors within jumps are placed where they are in the source */
+    program = "\n\n:f{\n+1{\"a\"};}\n!(f!){0}";
+    result = qrt_evaluate(interpreter, (const uint8_t *)program, strlen(program), NULL);
+    check(qrt_result_error_position(result) == 12, "errors within jumps have their position in the source");
+    check(qrt_result_error_line(result) == 3, "errors within jumps have their line in the source");
+    qrt_result_free(result);
+
+    result = qrt_evaluate(interpreter, (const uint8_t *)"$", 1, "[1");
+    check(!qrt_result_ok(result), "invalid inputs are thrown as errors");
+    check(strncmp(qrt_result_error(result), "Invalid input: ", 15) == 0, "invalid inputs say so");
+    qrt_result_free(result);
+
+    result = qrt_evaluate(NULL, (const uint8_t *)"1", 1, NULL);
+    check(equal(qrt_result_error(result), "No interpreter given"), "a NULL interpreter is thrown as an error");
+    qrt_result_free(result);
+
+    qrt_interpreter_free(interpreter);
+
+    return failures > 0;
+}
@@ -0,0 +1,6 @@
+use std::io;
+
+//The QRT language server, for editors to start and talk to over standard input and output
+fn main() -> io::Result<()> {
+    qrt::lsp::serve(io::stdin().lock(), io::stdout().lock())
+}
@@ -0,0 +1,9 @@
+mod qrt;
+#[cfg(feature = "python")]
+pub use qrt::python;
+pub use qrt::{
+    debug, evaluate, ffi, files, format, golden, helpers, interpreter, io, json, lint, literal,
+    lsp, operate, profile, session, snapshot, structs, syntax, threads, trace,
+};
+
+pub mod tests;
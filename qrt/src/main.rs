@@ -1,17 +1,43 @@
-use std::{env, fs, vec::Vec};
+use std::{env, fs, io, path::Path, process, vec::Vec};
 
-mod qrt;
-use qrt::{evaluate::evaluate, helpers::unwrap_evaluation, structs::Var};
-
-pub mod tests;
+use qrt::{
+    debug::Debugger,
+    evaluate::evaluate,
+    format::format,
+    golden::{self, Outcome},
+    helpers::unwrap_evaluation,
+    interpreter::Interpreter,
+    io::StdIo,
+    lint,
+    profile::Profiler,
+    structs::Var,
+    syntax::{line_column, SyntaxError},
+    trace::Tracer,
+};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < 4 {
+    match args.get(1).map(String::as_str) {
+        Some("fmt") => return fmt(&args[2..]),
+        Some("lint") => return lint(&args[2..]),
+        Some("debug") => return debug(&args[2..]),
+        Some("trace") => return trace(&args[2..]),
+        Some("profile") => return profile(&args[2..]),
+        Some("test") => return test(&args[2..]),
+        _ => (),
+    }
+
+    if args.len() < 3 {
         println!(
             "Not enough arguments provided. Please provide arguments in the following order:
-QRT file name (no extension), debug number (0-3)"
+QRT file name (no extension), debug number (0-3)
+Or, to format a file: fmt, QRT file name, and optionally --check
+Or, to lint a file: lint, QRT file name
+Or, to step through a file: debug, QRT file name
+Or, to trace every operator a file applies: trace, QRT file name
+Or, to profile a file: profile, QRT file name
+Or, to run golden file tests: test, directory, and optionally --update"
         );
         return;
     }
@@ -26,7 +52,7 @@ QRT file name (no extension), debug number (0-3)"
         _ => (false, false),
     };
 
-    let file: Vec<u8> = if let Ok(s) = fs::read_to_string(format!("{}.qrt", &args[1])) {
+    let file: Vec<u8> = if let Ok(s) = fs::read_to_string(qrt_path(&args[1])) {
         s.into_bytes()
     } else {
         println!("No such QRT file found");
@@ -35,3 +61,217 @@ QRT file name (no extension), debug number (0-3)"
 
     unwrap_evaluation(evaluate(&file, &Var::void()), showstack, showmap);
 }
+
+//Files are named without their extension, but a name that already has it is taken as it is
+fn qrt_path(name: &str) -> String {
+    if name.ends_with(".qrt") {
+        name.to_string()
+    } else {
+        format!("{}.qrt", name)
+    }
+}
+
+//Reads the QRT file a subcommand was given, exiting with 2 if it can't be
+fn read_qrt(name: Option<&String>) -> (String, Vec<u8>) {
+    let path = match name {
+        Some(name) => qrt_path(name),
+        None => {
+            println!("Please provide the name of a QRT file");
+            process::exit(2);
+        }
+    };
+
+    match fs::read(&path) {
+        Ok(file) => (path, file),
+        Err(_) => {
+            println!("No such QRT file found");
+            process::exit(2);
+        }
+    }
+}
+
+//Reports a file that couldn't be read as QRT and exits with 2
+fn syntax_failure(doing: &str, path: &str, file: &[u8], (msg, on): SyntaxError) -> ! {
+    let (line, column) = line_column(file, on);
+    println!(
+        "Could not {} {} (line {}, character {}):",
+        doing,
+        path,
+        line + 1,
+        column + 1
+    );
+    println!("{}", msg);
+    process::exit(2);
+}
+
+//Formats the given file in place, or with --check only reports whether it needs formatting (exiting with 1 if it does)
+fn fmt(args: &[String]) {
+    let check = args.iter().any(|arg| arg == "--check");
+    let (path, file) = read_qrt(args.iter().find(|arg| *arg != "--check"));
+
+    let formatted = match format(&file) {
+        Ok(formatted) => formatted,
+        Err(error) => syntax_failure("format", &path, &file, error),
+    };
+
+    if check {
+        if formatted != file {
+            println!("{} is not formatted", path);
+            process::exit(1);
+        }
+    } else if formatted != file && fs::write(&path, formatted).is_err() {
+        println!("Error in writing file");
+        process::exit(2);
+    }
+}
+
+//Prints every warning in the given file with where it was found, exiting with 1 if there were any
+fn lint(args: &[String]) {
+    let (path, file) = read_qrt(args.first());
+
+    let warnings = match lint::lint(&file) {
+        Ok(warnings) => warnings,
+        Err(error) => syntax_failure("lint", &path, &file, error),
+    };
+
+    for warning in &warnings {
+        let (line, column) = line_column(&file, warning.span.start);
+        let (endline, endcolumn) = line_column(&file, warning.span.end);
+        println!(
+            "{}:{}:{}-{}:{}: warning[{}]: {}",
+            path,
+            line + 1,
+            column + 1,
+            endline + 1,
+            endcolumn + 1,
+            warning.code,
+            warning.message
+        );
+    }
+
+    if !warnings.is_empty() {
+        process::exit(1);
+    }
+}
+
+//Runs the given file under the step debugger, which takes its commands from the console
+fn debug(args: &[String]) {
+    let (_, file) = read_qrt(args.first());
+
+    println!("Stopped before the first step, h shows every command");
+
+    let mut interpreter = Interpreter::new().with_hook(Debugger::new(&file, StdIo));
+
+    if let Some(v) = unwrap_evaluation(interpreter.evaluate(&file, &Var::void()), false, false) {
+        println!("Finished with {}", v.represent());
+    }
+}
+
+//Runs the given file, writing every operator it applies to standard error so its own output is left alone
+fn trace(args: &[String]) {
+    let (_, file) = read_qrt(args.first());
+
+    let mut interpreter = Interpreter::new().with_hook(Tracer::new(&file, io::stderr()));
+
+    unwrap_evaluation(interpreter.evaluate(&file, &Var::void()), false, false);
+}
+
+//Runs the given file, then prints where the time went and writes it as folded stacks next to the file
+fn profile(args: &[String]) {
+    let (path, file) = read_qrt(args.first());
+
+    let mut interpreter = Interpreter::new().with_hook(Profiler::new(&file));
+
+    unwrap_evaluation(interpreter.evaluate(&file, &Var::void()), false, false);
+
+    let profiler = &mut interpreter.hook;
+    profiler.finish();
+
+    println!("\n{}", profiler.table());
+
+    let folded = path.trim_end_matches(".qrt").to_string() + ".folded";
+    if fs::write(&folded, profiler.folded()).is_err() {
+        println!("Error in writing file");
+        process::exit(2);
+    }
+    println!("Folded stacks written to {}", folded);
+}
+
+//Runs every golden file test in the given directory (or the current one), along with the test jumps they define,
+//exiting with 1 if any of them failed.
+//With --update, tests that don't match have their expectations rewritten instead.
+fn test(args: &[String]) {
+    let update = args.iter().any(|arg| arg == "--update");
+    let dir = args
+        .iter()
+        .find(|arg| *arg != "--update")
+        .map_or(".", String::as_str);
+
+    let paths = match golden::discover(Path::new(dir)) {
+        Ok(paths) => paths,
+        Err(error) => {
+            println!("Could not find tests in {}: {}", dir, error);
+            process::exit(2);
+        }
+    };
+
+    //How many tests passed, failed and were updated
+    let mut counts = [0, 0, 0];
+
+    for path in paths {
+        report(
+            &path.display().to_string(),
+            golden::run(&path, update),
+            &mut counts,
+        );
+
+        //Test jumps are run after the file itself, each on their own
+        if let Ok(program) = fs::read(&path) {
+            for test in golden::test_jumps(&program) {
+                report(
+                    &format!("{}:{}", path.display(), test.name),
+                    Ok(golden::run_test(&path, &program, &test)),
+                    &mut counts,
+                );
+            }
+        }
+    }
+
+    let [passed, failed, updated] = counts;
+    println!(
+        "\n{} passed, {} failed, {} updated",
+        passed, failed, updated
+    );
+
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+//Prints how a test went, with every way it failed, and counts it
+fn report(name: &str, outcome: io::Result<Outcome>, counts: &mut [usize; 3]) {
+    match outcome {
+        Ok(Outcome::Passed) => {
+            println!("test {} ... ok", name);
+            counts[0] += 1;
+        }
+        Ok(Outcome::Failed(failures)) => {
+            println!("test {} ... FAILED", name);
+            for failure in failures {
+                println!("{}", failure.trim_end());
+            }
+            counts[1] += 1;
+        }
+        Ok(Outcome::Updated) => {
+            println!("test {} ... updated", name);
+            counts[2] += 1;
+        }
+        Err(error) => {
+            println!(
+                "test {} ... FAILED\nCould not run the test: {}",
+                name, error
+            );
+            counts[1] += 1;
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use super::{
+    evaluate::evaluate_from,
+    interpreter::{Hook, Interpreter, Step},
+    io::{BufferIo, Io, StdIo},
+    syntax::line_column,
+};
+use std::collections::BTreeSet;
+
+const HELP: &str = "s, step          run to the next expression, stepping into any ! evaluation
+n, next          run to the next expression, stepping over any ! evaluation
+o, out           run until the current ! evaluation gives its value
+c, continue      run until a breakpoint is reached
+b, break LINE    stop whenever LINE is reached
+d, delete LINE   remove the breakpoint on LINE
+stack            show the stack, with the most recent value last
+map              show every alias and its value
+p, print EXPR    evaluate EXPR with the aliases that are currently defined
+w, watch EXPR    evaluate EXPR at every stop
+u, unwatch N     stop watching the Nth watch expression
+q, quit          stop the program
+h, help          show this
+An empty line runs the same way as the last time the program was run.
+";
+
+//How far to run before stopping again, depths being those of the steps the evaluation was at when told to run
+enum Mode {
+    Step,
+    Over(usize),
+    Out(usize),
+    Continue,
+}
+
+//A step debugger, hooked into an interpreter to stop evaluation on breakpoints or after each step and take commands.
+//Commands are read from and everything is shown on its console, which is separate from the interpreter's own.
+pub struct Debugger<I: Io = StdIo> {
+    pub io: I,
+    program: Vec<u8>,
+    breakpoints: BTreeSet<usize>,
+    watches: Vec<Vec<u8>>,
+    mode: Mode,
+    line: Option<usize>,
+    last: String, //The last command that ran the program, which an empty line repeats
+}
+
+impl<I: Io> Debugger<I> {
+    //A debugger for the given program, which stops before its first step
+    pub fn new(program: &[u8], io: I) -> Debugger<I> {
+        Debugger {
+            io,
+            program: program.to_vec(),
+            breakpoints: BTreeSet::new(),
+            watches: Vec::new(),
+            mode: Mode::Step,
+            line: None,
+            last: "step".to_string(),
+        }
+    }
+
+    //Output is only for whoever is debugging, so failing to write it isn't worth stopping the program over
+    fn show(&mut self, text: &str) {
+        let _ = self.io.write_out(text.as_bytes());
+    }
+
+    //Shows where evaluation has stopped, with the line it's on and every watch expression
+    fn show_stop(&mut self, step: &Step) {
+        let (source, at, place) = match step.origin {
+            Some(origin) => (self.program.as_slice(), origin + step.on, "Line"),
+            None => (step.program, step.on, "Gestalt code, line"),
+        };
+
+        let (line, column) = line_column(source, at);
+        let text = source.split(|c| *c == b'\n').nth(line).unwrap_or_default();
+
+        let mut shown = format!(
+            "{} {}, character {} (depth {}):\n{:>5} | {}\n      | {}^\n",
+            place,
+            line + 1,
+            column + 1,
+            step.depth,
+            line + 1,
+            String::from_utf8_lossy(text).trim_end(),
+            " ".repeat(column)
+        );
+
+        for (i, watch) in self.watches.iter().enumerate() {
+            shown += &format!(
+                "{}: {} = {}\n",
+                i + 1,
+                String::from_utf8_lossy(watch),
+                watch_value(step, watch)
+            );
+        }
+
+        self.show(&shown);
+    }
+
+    //Takes commands until one of them continues evaluation
+    fn prompt(&mut self, step: &Step) -> Result<(), String> {
+        loop {
+            self.show("> ");
+
+            let command = match self.io.read_line() {
+                Ok(Some(line)) => String::from_utf8_lossy(&line).trim().to_string(),
+
+                //Once there are no more commands, the program is left to finish
+                _ => {
+                    self.mode = Mode::Continue;
+                    return Ok(());
+                }
+            };
+
+            let command = if command.is_empty() {
+                self.last.clone()
+            } else {
+                command
+            };
+
+            let (name, argument) = command.split_once(' ').unwrap_or((&command, ""));
+            let argument = argument.trim();
+
+            match name {
+                "s" | "step" => self.mode = Mode::Step,
+                "n" | "next" => self.mode = Mode::Over(step.depth),
+                "o" | "out" => self.mode = Mode::Out(step.depth),
+                "c" | "continue" => self.mode = Mode::Continue,
+                "q" | "quit" => return Err("Stopped by the debugger".to_string()),
+
+                "b" | "break" | "d" | "delete" => {
+                    match argument.parse::<usize>() {
+                        Ok(line) if line > 0 => {
+                            if name.starts_with('b') {
+                                self.breakpoints.insert(line);
+                                self.show(&format!("Breakpoint set on line {}\n", line));
+                            } else if self.breakpoints.remove(&line) {
+                                self.show(&format!("Breakpoint removed from line {}\n", line));
+                            } else {
+                                self.show(&format!("No breakpoint on line {}\n", line));
+                            }
+                        }
+                        _ => self.show("Expected a line number\n"),
+                    }
+                    continue;
+                }
+
+                "stack" => {
+                    let mut shown = String::new();
+                    for element in step.stack.iter().rev() {
+                        shown += &element.represent();
+                        shown.push('\n');
+                    }
+                    self.show(&shown);
+                    continue;
+                }
+
+                "map" => {
+                    let mut aliases: Vec<_> = step.map.iter().collect();
+                    aliases.sort_by(|a, b| a.0.cmp(b.0));
+
+                    let mut shown = String::new();
+                    for (name, var) in aliases {
+                        shown += &format!("{}: {}\n", name, var.represent());
+                    }
+                    self.show(&shown);
+                    continue;
+                }
+
+                "p" | "print" => {
+                    let value = watch_value(step, argument.as_bytes());
+                    self.show(&format!("{}\n", value));
+                    continue;
+                }
+
+                "w" | "watch" => {
+                    let value = watch_value(step, argument.as_bytes());
+                    self.watches.push(argument.as_bytes().to_vec());
+                    self.show(&format!(
+                        "{}: {} = {}\n",
+                        self.watches.len(),
+                        argument,
+                        value
+                    ));
+                    continue;
+                }
+
+                "u" | "unwatch" => {
+                    match argument.parse::<usize>() {
+                        Ok(i) if i > 0 && i <= self.watches.len() => {
+                            self.watches.remove(i - 1);
+                        }
+                        _ => self.show("No such watch expression\n"),
+                    }
+                    continue;
+                }
+
+                "h" | "help" => {
+                    self.show(HELP);
+                    continue;
+                }
+
+                _ => {
+                    self.show("Unknown command, h shows every command\n");
+                    continue;
+                }
+            }
+
+            self.last = command;
+            return Ok(());
+        }
+    }
+}
+
+impl<I: Io> Hook for Debugger<I> {
+    fn step(&mut self, step: &Step) -> Result<(), String> {
+        //Breakpoints stop evaluation as it reaches their line, rather than on every step along it
+        let line = step
+            .origin
+            .map(|origin| line_column(&self.program, origin + step.on).0 + 1);
+        let reached = line.is_some() && line != self.line;
+        if line.is_some() {
+            self.line = line;
+        }
+
+        let stop = match self.mode {
+            Mode::Step => true,
+            Mode::Over(depth) => step.depth <= depth,
+            Mode::Out(depth) => step.depth < depth,
+            Mode::Continue => false,
+        } || (reached && line.is_some_and(|line| self.breakpoints.contains(&line)));
+
+        if !stop {
+            return Ok(());
+        }
+
+        self.show_stop(step);
+        self.prompt(step)
+    }
+}
+
+//Evaluates a watch expression as though it came right after the code being stepped through, so it can use the
+//aliases and jumps defined there. Anything it prints or reads goes nowhere.
+fn watch_value(step: &Step, expression: &[u8]) -> String {
+    let program = [step.program, b"\n", expression].concat();
+
+    match evaluate_from(
+        &mut Interpreter::with_io(BufferIo::default()),
+        &program,
+        step.input,
+        step.map.clone(),
+        step.program.len() + 1,
+        None,
+        0,
+    ) {
+        Ok(var) => var.represent(),
+        Err((msg, ..)) => format!("Error: {}", msg),
+    }
+}
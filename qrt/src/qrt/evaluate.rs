@@ -1,29 +1,258 @@
-use super::{helpers::*, structs::*};
+use super::{
+    files::{access_file, FileOperation},
+    helpers::*,
+    interpreter::{Hook, Interpreter, Step},
+    io::Io,
+    operate::operate,
+    structs::*,
+    threads::parallel_map,
+};
 
 use std::{
     collections::{HashMap, VecDeque},
-    fs,
-    io::Read,
-    path::Path,
+    mem,
+    sync::Arc,
+    task::Poll,
+    time::Duration,
     vec::Vec,
 };
 
 extern crate rand;
 use rand::random;
 
-//This is the big one, our 750-line function that evaluates all QRT code with a little help.
+//Evaluates QRT code attached to the real console.
 pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
-    //This is used to store the state of our program
-    let mut stack: VecDeque<Abstract> = VecDeque::new();
+    evaluate_with(&mut Interpreter::new(), program, input)
+}
+
+//Evaluates QRT code within the given interpreter, from the beginning and with no aliases defined.
+pub fn evaluate_with<I: Io, H: Hook>(
+    interpreter: &mut Interpreter<I, H>,
+    program: &[u8],
+    input: &Var,
+) -> Evaluation {
+    Task::new(program, input).finish(interpreter)
+}
 
-    //This is used to store declared variables within the program
-    let mut map: HashMap<String, Var> = HashMap::new();
+//Evaluates QRT code from on, with the aliases in map already defined, as if the program had been running all along.
+//origin is where the program starts within the one the interpreter was given (None for Gestalts evaluated as code),
+//and depth is how many evaluations deep it is, both are only kept to tell the interpreter's hook where evaluation is.
+pub fn evaluate_from<I: Io, H: Hook>(
+    interpreter: &mut Interpreter<I, H>,
+    program: &[u8],
+    input: &Var,
+    map: HashMap<String, Var>,
+    on: usize,
+    origin: Option<usize>,
+    depth: usize,
+) -> Evaluation {
+    let scope = Scope { map, killidon: 0 };
+    Task::within(program, input, scope, on, origin, depth).finish(interpreter)
+}
+
+//Like evaluate_from, but within a scope that's kept once evaluation ends (even with an error), so it can be carried on.
+pub fn evaluate_in<I: Io, H: Hook>(
+    interpreter: &mut Interpreter<I, H>,
+    program: &[u8],
+    input: &Var,
+    scope: &mut Scope,
+    on: usize,
+    origin: Option<usize>,
+    depth: usize,
+) -> Evaluation {
+    let mut task = Task::within(program, input, mem::take(scope), on, origin, depth);
+    let evaluation = task.finish(interpreter);
+    *scope = task.scope;
+    evaluation
+}
 
-    //This is used to store our place in evaluation
-    let mut on = 0;
+//One evaluation, with everything it needs to carry on from where it is.
+//Its program is code[start..], as jumps are evaluated from where they are within the code that defined them.
+//returned holds the value of the evaluation it started with !, once that has given one.
+#[derive(Clone, Debug)]
+pub(crate) struct Frame {
+    pub(crate) code: Arc<[u8]>,
+    pub(crate) start: usize,
+    pub(crate) input: Var,
+    pub(crate) scope: Scope,
+    pub(crate) stack: VecDeque<Abstract>,
+    pub(crate) on: usize,
+    pub(crate) origin: Option<usize>,
+    pub(crate) depth: usize,
+    pub(crate) returned: Option<Var>,
+}
 
-    //Used to assign killids to loops
-    let mut killidon: usize = 0;
+//What evaluating a frame came to
+enum Progress {
+    Paused,            //It ran out of steps
+    Evaluating(Frame), //It started an evaluation with !, which has to give a value before it can carry on
+    Done(Evaluation),
+}
+
+//An evaluation that can be run a few steps at a time, so whatever is running it can do other things in between.
+//Every evaluation started with ! is kept as a frame above the one that started it, so none of them are ever left
+//halfway through a Rust function, which is what lets evaluation stop after any step and carry on later.
+#[derive(Clone, Debug)]
+pub struct Task {
+    pub(crate) frames: Vec<Frame>,
+    pub(crate) scope: Scope, //The scope of the program itself, once it has finished
+    pub(crate) thrown_at: Option<usize>, //Where in the program the error it finished with was thrown, see thrown_at
+}
+
+impl Task {
+    //A task evaluating the program from the beginning, with no aliases defined
+    pub fn new(program: &[u8], input: &Var) -> Task {
+        Task::within(program, input, Scope::default(), 0, Some(0), 0)
+    }
+
+    //A task evaluating the program from on within the scope, like evaluate_from
+    pub fn within(
+        program: &[u8],
+        input: &Var,
+        scope: Scope,
+        on: usize,
+        origin: Option<usize>,
+        depth: usize,
+    ) -> Task {
+        Task {
+            frames: vec![Frame {
+                code: Arc::from(program),
+                start: 0,
+                input: input.clone(),
+                scope,
+                stack: VecDeque::new(),
+                on,
+                origin,
+                depth,
+                returned: None,
+            }],
+            scope: Scope::default(),
+            thrown_at: None,
+        }
+    }
+
+    //Runs the evaluation for at most the given number of steps (the ones a hook is told about), giving its value or
+    //error once it has finished, or Pending if it has more to do. A task that has finished gives an error.
+    pub fn run<I: Io, H: Hook>(
+        &mut self,
+        interpreter: &mut Interpreter<I, H>,
+        mut steps: usize,
+    ) -> Poll<Evaluation> {
+        loop {
+            let frame = match self.frames.last_mut() {
+                Some(frame) => frame,
+                None => {
+                    return Poll::Ready(Err((
+                        "Evaluation has already finished".to_string(),
+                        0,
+                        0,
+                        VecDeque::new(),
+                        self.scope.map.clone(),
+                    )))
+                }
+            };
+
+            let mut evaluation = match evaluate_frame(interpreter, frame, &mut steps) {
+                Progress::Paused => return Poll::Pending,
+                Progress::Evaluating(frame) => {
+                    self.frames.push(frame);
+                    continue;
+                }
+                Progress::Done(evaluation) => evaluation,
+            };
+
+            //An error's position is within the evaluation it was thrown in, so where that is in the program is kept
+            //as well. Gestalt code isn't part of the program, so its errors are placed where it was evaluated.
+            if let Err((_, on, ..)) = &evaluation {
+                self.thrown_at = self.frames.iter().rev().enumerate().find_map(|(i, frame)| {
+                    frame
+                        .origin
+                        .map(|origin| origin + if i == 0 { *on } else { frame.on })
+                });
+            }
+
+            //Errors are given by every evaluation they're within, along with where each evaluated the next
+            loop {
+                let frame = self.frames.pop().unwrap_or_else(|| unreachable!());
+
+                let parent = match self.frames.last_mut() {
+                    Some(parent) => parent,
+                    None => {
+                        self.scope = frame.scope;
+                        return Poll::Ready(evaluation);
+                    }
+                };
+
+                match evaluation {
+                    Ok(var) => {
+                        parent.returned = Some(var);
+                        break;
+                    }
+                    Err((msg, funcon, funclineon, stack, map)) => {
+                        let separator = match parent.stack.get(1) {
+                            Some(Abstract::Var(Var::Linear(_))) => " \n",
+                            _ => "",
+                        };
+
+                        evaluation = Err((
+                            msg + separator
+                                + "(In function evaluated at "
+                                + &format!("{}", parent.on)
+                                + ")",
+                            funcon,
+                            funclineon,
+                            stack,
+                            map,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    //Runs the evaluation until it has finished
+    pub fn finish<I: Io, H: Hook>(&mut self, interpreter: &mut Interpreter<I, H>) -> Evaluation {
+        loop {
+            if let Poll::Ready(evaluation) = self.run(interpreter, usize::MAX) {
+                return evaluation;
+            }
+        }
+    }
+
+    //Everything the program defined, once it has finished
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    //Where in the program the error the task finished with was thrown, once it has, unlike the error's own position
+    //(which is within the jump it was thrown in). None if it didn't throw one, or it was thrown outside the program.
+    pub fn thrown_at(&self) -> Option<usize> {
+        self.thrown_at
+    }
+}
+
+//This is the big one, the function that evaluates all QRT code with a little help (mostly from operate).
+//It runs the frame until it finishes, starts another evaluation, or runs out of steps, keeping where it was up to.
+fn evaluate_frame<I: Io, H: Hook>(
+    interpreter: &mut Interpreter<I, H>,
+    frame: &mut Frame,
+    steps: &mut usize,
+) -> Progress {
+    let Frame {
+        code,
+        start,
+        input,
+        scope,
+        stack,
+        on: at,
+        origin,
+        depth,
+        returned,
+    } = frame;
+    let (code, origin, depth, input) = (&*code, *origin, *depth, &*input);
+    let program = &code[*start..];
+    let Scope { map, killidon } = scope;
+    let mut on = *at;
 
     //This macro coerces a Var to the desired type, throwing an error if it fails.
     macro_rules! unpack_var {
@@ -47,17 +276,6 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
         }};
     }
 
-    //This is for use inside operation closures where the return type is a simpler Result with only a msg.
-    macro_rules! cstring_from_utf8 {
-        ($utf8:expr) => {{
-            if let Ok(s) = String::from_utf8($utf8) {
-                s
-            } else {
-                return Err("Invalid Gestalt chars");
-            }
-        }};
-    }
-
     //This is a common piece of code for operations on the stack
     macro_rules! clear_and_progress {
         () => {
@@ -69,53 +287,22 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
         };
     }
 
-    //This macro generates mutliple type match statements for multiple operation variations (Linear-Linear, Gestalt-Linear, etc.)
-    macro_rules! multi_operate {
-        ( $( ($vartypea:tt, $vartypeb:tt, $outtype:tt $operation:expr) ),*) => {{
-            match (unpack_stack!(1), unpack_stack!(0)) {
-
-                $(
-                    (Abstract::Var(Var::$vartypea(a)), Abstract::Var(Var::$vartypeb(b))) => {
-                        let result = $operation(
-                            a.clone(),
-                            b.clone()
-                        );
-
-                        clear_and_progress!();
-
-                        match result {
-                            Ok(result) => {stack.push_front(Abstract::Var(Var::$outtype(result)));}
-                            Err(error) => {return_error!(error)}
-                        }
-                    }
-                )*
-
-                (Abstract::Var(Var::Void(_)), _) | (_, Abstract::Var(Var::Void(_))) => {
-                    clear_and_progress!();
-
-                    stack.push_front(Abstract::Var(Var::void()));
-                }
-
-                _ => {return_error!("Invalid operand types")}
-            }
-        }};
-    }
-
     //This macro finds the current line, and returns the given error message along with the slew of sometimes-needed debug info
     macro_rules! return_error {
         ($errtext:expr) => {{
-            let (mut i, mut linecount) = (on, 0);
-
-            while i != 0 {
-                //Checks for newlines
-                if program[i] == 10 {
-                    linecount += 1;
-                }
-
-                i -= 1;
-            }
-
-            return Result::Err(($errtext.to_string(), on, linecount, stack, map));
+            //Checks for newlines, on can be past the end of the program if it ended unexpectedly
+            let linecount = program[..on.min(program.len())]
+                .iter()
+                .filter(|c| **c == 10)
+                .count();
+
+            return Progress::Done(Result::Err((
+                $errtext.to_string(),
+                on,
+                linecount,
+                mem::take(stack),
+                map.clone(),
+            )));
         }};
     }
 
@@ -143,22 +330,85 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
         };
     }
 
+    //This macro finds the end of a secondary argument, throwing an error if the program ends before it does.
+    macro_rules! find_bracket_pair {
+        ($on:expr) => {
+            if let Some(on) = find_bracket_pair(program, $on) {
+                on
+            } else {
+                return_error!("No closing bracket found");
+            }
+        };
+    }
+
+    //This macro reads a plainly written name up to (not including) any of the given bytes.
+    macro_rules! read_name {
+        ($( $end:expr ),*) => {{
+            let mut name: Vec<u8> = Vec::new();
+
+            loop {
+                match program.get(on) {
+                    $( Some($end) )|* => break,
+                    Some(c) => name.push(*c),
+                    None => return_error!("Program ended in the middle of a name"),
+                }
+
+                on += 1;
+            }
+
+            name
+        }};
+    }
+
+    //This macro gives the hook where evaluation is and everything it has
+    macro_rules! step {
+        () => {
+            Step {
+                program,
+                on,
+                origin,
+                depth,
+                input,
+                stack,
+                map,
+            }
+        };
+    }
+
     //This is the main evaluation loop
-    'main: loop {
+    loop {
         //print!("{}", program[on] as char); //Silly debug tool
 
         //Returns if the end of the program has been reached or exceeded
         if on >= program.len() {
-            return match stack.pop_front() {
+            return Progress::Done(match stack.pop_front() {
                 Some(Abstract::Var(v)) => Ok(v),
 
                 _ => Ok(Var::void()),
-            };
+            });
+        }
+
+        //The hook is told about every step that does something, so whitespace and comments are skipped over.
+        //A ! that has just been given the value of its evaluation was already told about when it started it.
+        if returned.is_none() && !matches!(program[on], 9 | 32 | 13 | 10 | b',' | b'\\') {
+            if *steps == 0 {
+                *at = on;
+                return Progress::Paused;
+            }
+            *steps -= 1;
+
+            if interpreter.is_cancelled() {
+                return_error!("Evaluation cancelled");
+            }
+
+            if let Err(error) = interpreter.hook.step(&step!()) {
+                return_error!(error);
+            }
         }
 
         match program[on] {
             //Space, tab, carriage return, and new line. Essentially whitespace skipping.
-            19 | 32 | 13 | 10 => {
+            9 | 32 | 13 | 10 => {
                 on += 1;
             }
 
@@ -173,12 +423,8 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
 
                 //In the case where a trailing comment exists in the program, the evaluator will detect that on has gone out of bounds
                 //And continue back to the loop head, where the evaluator will return the head of the stack as usual.
-                while program[on] != b'\\' {
+                while on < program.len() && program[on] != b'\\' {
                     on += 1;
-
-                    if on >= program.len() {
-                        continue 'main;
-                    }
                 }
 
                 on += 1;
@@ -217,7 +463,11 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
 
                 loop {
                     on += 1;
-                    match program[on] {
+                    match if let Some(c) = program.get(on) {
+                        *c
+                    } else {
+                        return_error!("No closing quote found for Gestalt literal");
+                    } {
                         //Matches for quotes (gestalt termination or escaped quote)
                         b'"' => {
                             if !escape {
@@ -231,6 +481,7 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
                         //Matches for backslashes (escape or escaped backslash)
                         b'\\' => {
                             if escape {
+                                escape = false;
                                 gestalt.push(b'\\');
                             } else {
                                 escape = true
@@ -309,7 +560,7 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
                                     0,
                                     "Invalid kill variable name given to loop"
                                 )),
-                                Var::Kill(killidon),
+                                Var::Kill(*killidon),
                             );
 
                             //pops off killid and baby loop
@@ -317,17 +568,17 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
                             stack.pop_front();
 
                             //pushes on complete loop with correct killid, and the loop's starting position as a linear
-                            stack.push_front(Abstract::Loop(killidon));
+                            stack.push_front(Abstract::Loop(*killidon));
                             stack.push_front(Abstract::Var(Var::Linear((on + 1) as f64)));
 
                             //advances killidon, and the on into the loop code
-                            killidon += 1;
+                            *killidon += 1;
                             on += 1;
                         } else if o == &b'?' {
                             if unpack_var!(Linear, 0, "Invalid conditional type") > 0.0 {
                                 on += 1;
                             } else {
-                                on = find_bracket_pair(program, on + 1);
+                                on = find_bracket_pair!(on + 1);
                             }
 
                             stack.pop_front();
@@ -349,16 +600,13 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
 
                 on += 1;
 
-                let mut name: Vec<u8> = Vec::new();
-
-                while !(program[on] == b'{' || program[on] == b'!' || program[on] == b'_') {
-                    name.push(program[on]);
-                    on += 1;
-                }
+                let name = read_name!(b'{', b'!', b'_');
 
+                //The void literal stands in for the name, so it's skipped rather than evaluated again
                 if program[on] == b'_' {
                     stack.push_front(Abstract::Operator(operator));
                     stack.push_front(Abstract::Var(Var::void()));
+                    on += 1;
                     continue;
                 }
 
@@ -375,12 +623,7 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
             b':' => {
                 on += 1;
 
-                let mut name: Vec<u8> = Vec::new();
-
-                while !(program[on] == b'{' || program[on] == b'!') {
-                    name.push(program[on]);
-                    on += 1;
-                }
+                let name = read_name!(b'{', b'!');
 
                 if program[on] == b'!' {
                     return_error!("Bangs (!) not allowed in function names")
@@ -390,19 +633,14 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
                 map.insert(string_from_utf8!(name) + "!", Var::Linear((on + 1) as f64));
 
                 //Skips to after the bracket for find_bracket_pair to work correctly
-                on = find_bracket_pair(program, on + 2);
+                on = find_bracket_pair!(on + 1);
             }
 
             //Alias referencing
             b'(' => {
                 on += 1;
 
-                let mut varname: Vec<u8> = Vec::new();
-
-                while program[on] != b')' {
-                    varname.push(program[on]);
-                    on += 1;
-                }
+                let varname = read_name!(b')');
                 on += 1;
 
                 //Checks if either varname or varname! exists, since jumps (functions kinda) add bangs in definition
@@ -413,17 +651,21 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
                 };
 
                 if let Var::Kill(killid) = var {
+                    if !stack.contains(&Abstract::Loop(killid)) {
+                        return_error!("Killed loop is not running");
+                    }
+
                     //Destroys all values until reaching the loop
                     while stack.get(1) != Some(&Abstract::Loop(killid)) {
                         stack.pop_front();
                     }
 
                     //Sets the on to after the killed loop
-                    on = find_bracket_pair(
-                        program,
-                        unpack_var!(Linear, 0, "Error getting starting linear in loop kill") as i64
-                            as usize,
-                    );
+                    on = find_bracket_pair!(unpack_var!(
+                        Linear,
+                        0,
+                        "Error getting starting linear in loop kill"
+                    ) as usize);
 
                     //Removes both the loop and its starting position linear from the stack
                     stack.pop_front();
@@ -469,355 +711,429 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
                                     clear_and_progress!();
                                 }
 
-                                //ARTITHMETIC
+                                //MISC
 
-                                //Addition
-                                b'+' => {
-                                    multi_operate!(
+                                //Evaluation
+                                b'!' => match (unpack_stack!(0), unpack_stack!(1), returned.take())
+                                {
+                                    //Once the evaluation has given a value, it takes the place of the operation
+                                    (Abstract::Var(v), Abstract::Var(evaluated), Some(eva)) => {
+                                        interpreter.hook.applied(&step!(), a, evaluated, v, &eva);
 
-                                        (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {Ok(a + b)}),
+                                        clear_and_progress!();
 
-                                        (Linear, Gestalt, Linear|a: f64, b: Vec<u8>| -> Result<f64, &str> {
-                                            if let Ok(b) = cstring_from_utf8!(b).parse::<f64>() {
-                                                Ok(a + b)
-                                            } else {
-                                                Err("Could not coerce Gestalt to Linear")
-                                            }
-                                        }),
-
-                                        (Gestalt, Linear, Gestalt|a: Vec<u8>, b: f64| -> Result<Vec<u8>, &str> {
-                                            Ok((cstring_from_utf8!(a) + &format!("{}", b)).into())
-                                        }),
-
-                                        (Gestalt, Gestalt, Gestalt|a: Vec<u8>, b: Vec<u8>| -> Result<Vec<u8>, &str> {
-                                            Ok((cstring_from_utf8!(a) + &cstring_from_utf8!(b)).into())
-                                        }),
-
-                                        //Please note that all the disgusting, unperformant set cloning in this language was intended to be remedied with a kind of
-                                        //reference system, but that rust fundamentally does not allow multiple mutable references so that couldn't be done.
-                                        (Set, Linear, Set|a: Vec<Var>, b: f64| -> Result<Vec<Var>, &str> {
-                                            let mut newset = a.clone();
-                                            newset.push(Var::Linear(b));
-                                            Ok(newset)
-                                        }),
-
-                                        (Set, Gestalt, Set|a: Vec<Var>, b: Vec<u8>| -> Result<Vec<Var>, &str> {
-                                            let mut newset = a.clone();
-                                            newset.push(Var::Gestalt(b));
-                                            Ok(newset)
-                                        }),
-
-                                        (Set, Set, Set|a: Vec<Var>, b: Vec<Var>| -> Result<Vec<Var>, &str> {
-                                            let mut newset = a.clone();
-                                            newset.push(Var::Set(b));
-                                            Ok(newset)
-                                        })
-                                    );
-                                }
-                                //Subtraction
-                                b'-' => {
-                                    multi_operate!(
-                                        (Linear, Linear, Linear|a: f64, b:f64| -> Result<f64, &str> {Ok(a - b)}),
-
-                                        (Gestalt, Linear, Gestalt|a: Vec<u8>, b: f64| -> Result<Vec<u8>, &str> {
-                                            let mut newges = a.clone();
-                                            newges.remove(b as i64 as usize);
-                                            Ok(newges)
-                                        }),
-
-                                        (Set, Linear, Set|a: Vec<Var>, b: f64| -> Result<Vec<Var>, &str> {
-                                            let mut newset = a.clone();
-                                            newset.remove(b as i64 as usize);
-                                            Ok(newset)
-                                        })
-                                    )
-                                }
-                                //Multiplication
-                                b'*' => {
-                                    multi_operate!(
-                                        (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {Ok(a * b)}),
-
-                                        //Set concatenation
-                                        (Set, Set, Set|mut a: Vec<Var>, b: Vec<Var>| -> Result<Vec<Var>, &str> {
-                                            for var in b {a.push(var.clone())}
-                                            Ok(a)
-                                        })
-                                    )
-                                }
-                                //Division
-                                b'/' => {
-                                    multi_operate!(
-                                        (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {Ok(a / b)})
-                                    )
-                                }
-                                //Exponentiation
-                                b'^' => {
-                                    multi_operate!(
-                                        (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {Ok(a.powf(b))}),
+                                        stack.push_front(Abstract::Var(eva));
+                                    }
 
-                                        //Special cases for giving the length of sets and gestalts
-                                        (Gestalt, Void, Linear|a: Vec<u8>, _b: ()| -> Result<f64, &str> {Ok(a.len() as f64)}),
+                                    //Jumps are evaluated from where they are in the code, so the rest of it is there too
+                                    (Abstract::Var(v), Abstract::Var(evaluated), None) => {
+                                        let evaluation = match callable_frame(
+                                            code,
+                                            *start,
+                                            origin,
+                                            evaluated,
+                                            v,
+                                            depth + 1,
+                                        ) {
+                                            Ok(evaluation) => evaluation,
+                                            Err(error) => return_error!(error),
+                                        };
 
-                                        (Set, Void, Linear|a: Vec<Var>, _b: ()| -> Result<f64, &str> {Ok(a.len() as f64)})
-                                    )
-                                }
+                                        *at = on;
+                                        return Progress::Evaluating(evaluation);
+                                    }
 
-                                //LOGICAL
+                                    _ => return_error!("Invalid operand types"),
+                                },
 
-                                //And
-                                b'&' => {
-                                    multi_operate!(
-                                        (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {
-                                            if a > 0.0 && b > 0.0 {Ok(1.0)} else {Ok(0.0)}
-                                        })
-                                    )
+                                //File access
+                                b'@' => {
+                                    let (path, v) = match (unpack_stack!(1), unpack_stack!(0)) {
+                                        (Abstract::Var(Var::Gestalt(path)), Abstract::Var(v)) => {
+                                            (path, v)
+                                        }
+                                        _ => return_error!("Invalid operand types"),
+                                    };
+
+                                    match FileOperation::from_var(v)
+                                        .and_then(|operation| access_file(path, operation))
+                                    {
+                                        Ok(result) => {
+                                            interpreter.hook.applied(
+                                                &step!(),
+                                                a,
+                                                &Var::Gestalt(path.clone()),
+                                                v,
+                                                &result,
+                                            );
+
+                                            clear_and_progress!();
+
+                                            stack.push_front(Abstract::Var(result));
+                                        }
+                                        Err(error) => return_error!(error),
+                                    }
                                 }
-                                //Or
-                                b'|' => {
-                                    multi_operate!(
-                                        (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {
-                                            if a > 0.0 || b > 0.0 {Ok(1.0)} else {Ok(0.0)}
-                                        })
-                                    )
+
+                                //HOST
+
+                                //Calling a host function, registered on the interpreter by the Rust code running it
+                                b'h' => {
+                                    let (name, arguments) =
+                                        match (unpack_stack!(1), unpack_stack!(0)) {
+                                            (
+                                                Abstract::Var(Var::Gestalt(name)),
+                                                Abstract::Var(Var::Set(arguments)),
+                                            ) => (name, arguments),
+                                            _ => return_error!("Invalid operand types"),
+                                        };
+
+                                    let result = match interpreter
+                                        .function(&String::from_utf8_lossy(name))
+                                    {
+                                        Some(function) => function(arguments),
+                                        None => return_error!("Host function not found"),
+                                    };
+
+                                    match result {
+                                        Ok(result) => {
+                                            interpreter.hook.applied(
+                                                &step!(),
+                                                a,
+                                                &Var::Gestalt(name.clone()),
+                                                &Var::Set(arguments.clone()),
+                                                &result,
+                                            );
+
+                                            clear_and_progress!();
+
+                                            stack.push_front(Abstract::Var(result));
+                                        }
+                                        Err(error) => return_error!(format!(
+                                            "{} (In host function {})",
+                                            error,
+                                            String::from_utf8_lossy(name)
+                                        )),
+                                    }
                                 }
 
-                                //COMPARISON
+                                //CONCURRENCY
 
-                                //Equal to
-                                b'=' => {
-                                    multi_operate!(
-                                        (Void, Void, Linear|_a: (), _b: ()| -> Result<f64, &str> {Ok(1.0)}),
+                                //Spawning, which evaluates a jump or Gestalt of code on its own thread, giving its handle.
+                                //It gets its own interpreter, sharing the console and everything else but the hook.
+                                b's' => {
+                                    let (evaluated, v) = match (unpack_stack!(1), unpack_stack!(0))
+                                    {
+                                        (Abstract::Var(evaluated), Abstract::Var(v)) => {
+                                            (evaluated, v)
+                                        }
+                                        _ => return_error!("Invalid operand types"),
+                                    };
+
+                                    //Spawned evaluations aren't nested within anything, so they start at depth 0
+                                    let mut task =
+                                        match callable_frame(code, *start, origin, evaluated, v, 0)
+                                        {
+                                            Ok(frame) => Task {
+                                                frames: vec![frame],
+                                                scope: Scope::default(),
+                                                thrown_at: None,
+                                            },
+                                            Err(error) => return_error!(error),
+                                        };
+                                    let mut worker = interpreter.spawned();
 
-                                        (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {
-                                            if a == b {Ok(1.0)} else {Ok(0.0)}
-                                        }),
+                                    let id = interpreter
+                                        .threads()
+                                        .spawn(move || task.finish(&mut worker));
+                                    let result = Var::Linear(id as f64);
 
-                                        (Gestalt, Gestalt, Linear|a: Vec<u8>, b: Vec<u8>| -> Result<f64, &str> {
-                                            if a == b {Ok(1.0)} else {Ok(0.0)}
-                                        }),
+                                    interpreter.hook.applied(&step!(), a, evaluated, v, &result);
 
-                                        (Set, Set, Linear|a: Vec<Var>, b: Vec<Var>| -> Result<f64, &str> {
-                                            if a == b {Ok(1.0)} else {Ok(0.0)}
-                                        })
-                                    )
-                                }
-                                //Greater than
-                                b'>' => {
-                                    multi_operate!(
-                                        (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {
-                                            if a > b {Ok(1.0)} else {Ok(0.0)}
-                                        }),
-
-                                        (Gestalt, Linear, Gestalt|a: Vec<u8>, b: f64| -> Result<Vec<u8>, &str> {
-                                            let mut newges = a.clone();
-                                            newges.truncate(a.len() - b as i64 as usize);
-                                            Ok(newges)
-                                        }),
-
-                                        (Set, Linear, Set|a: Vec<Var>, b: f64| -> Result<Vec<Var>, &str> {
-                                            let mut newset = a.clone();
-                                            newset.truncate(a.len() - b as i64 as usize);
-                                            Ok(newset)
-                                        })
-                                    )
-                                }
-                                //Less than
-                                b'<' => {
-                                    multi_operate!(
-                                        (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {
-                                            if a < b {Ok(1.0)} else {Ok(0.0)}
-                                        }),
-
-                                        (Gestalt, Linear, Gestalt|a: Vec<u8>, b: f64| -> Result<Vec<u8>, &str> {
-                                            Ok(a[b as i64 as usize..].to_vec())
-                                        }),
-
-                                        (Set, Linear, Set|a: Vec<Var>, b: f64| -> Result<Vec<Var>, &str> {
-                                            Ok(a[b as i64 as usize..].to_vec())
-                                        })
-                                    )
-                                }
+                                    clear_and_progress!();
 
-                                //MISC
+                                    stack.push_front(Abstract::Var(result));
+                                }
 
-                                //Evaluation
-                                b'!' => match (unpack_stack!(0), unpack_stack!(1)) {
-                                    (Abstract::Var(v), Abstract::Var(Var::Linear(jmp))) => {
-                                        //If the evaluation itself throws an error, that error and its interior stack/map are
-                                        //Given as the error, along with a notification of what function threw the error.
-                                        match evaluate(&program[*jmp as i64 as usize..], v) {
-                                            Ok(eva) => {
-                                                clear_and_progress!();
-                                                stack.push_front(Abstract::Var(eva))
-                                            }
-                                            Err((msg, funcon, funclineon, stack, map)) => {
-                                                return Result::Err((
-                                                    msg + " \n(In function evaluated at "
-                                                        + &format!("{}", on)
-                                                        + ")",
-                                                    funcon,
-                                                    funclineon,
-                                                    stack,
-                                                    map,
-                                                ))
-                                            }
+                                //Joining, which waits for the thread to finish and gives what it returned
+                                b'j' => {
+                                    let (thread, v) = match (unpack_stack!(1), unpack_stack!(0)) {
+                                        (Abstract::Var(thread), Abstract::Var(v)) => (thread, v),
+                                        _ => return_error!("Invalid operand types"),
+                                    };
+
+                                    let id = match handle(thread) {
+                                        Some(id) => id,
+                                        None => return_error!("Thread not found"),
+                                    };
+
+                                    let result = match interpreter.threads().join(id) {
+                                        Ok(Ok(result)) => result,
+                                        Ok(Err((msg, ..))) => {
+                                            return_error!(format!("{} (In thread {})", msg, id))
                                         }
-                                    }
+                                        Err(error) => return_error!(error),
+                                    };
+
+                                    interpreter.hook.applied(&step!(), a, thread, v, &result);
 
-                                    (Abstract::Var(v), Abstract::Var(Var::Gestalt(g))) => {
-                                        let eva = match evaluate(g, v) {
-                                            Ok(eva) => eva,
-                                            Err((msg, funcon, funclineon, stack, map)) => {
-                                                return Result::Err((
-                                                    msg + "(In function evaluated at "
-                                                        + &format!("{}", on)
-                                                        + ")",
-                                                    funcon,
-                                                    funclineon,
-                                                    stack,
-                                                    map,
-                                                ))
+                                    clear_and_progress!();
+
+                                    stack.push_front(Abstract::Var(result));
+                                }
+
+                                //Making a channel, giving its handle
+                                b'c' => {
+                                    let (primary, secondary) =
+                                        match (unpack_stack!(1), unpack_stack!(0)) {
+                                            (Abstract::Var(primary), Abstract::Var(secondary)) => {
+                                                (primary, secondary)
                                             }
+                                            _ => return_error!("Invalid operand types"),
                                         };
 
-                                        clear_and_progress!();
-
-                                        stack.push_front(Abstract::Var(eva));
-                                    }
+                                    let result =
+                                        Var::Linear(interpreter.threads().channel() as f64);
 
-                                    _ => return_error!("Invalid operand types"),
-                                },
+                                    interpreter.hook.applied(
+                                        &step!(),
+                                        a,
+                                        primary,
+                                        secondary,
+                                        &result,
+                                    );
 
-                                //Reading/writing files
-                                b'@' => match (unpack_stack!(1), unpack_stack!(0)) {
-                                    //For a gestalt and a void, we're just reading, no writing.
-                                    (
-                                        Abstract::Var(Var::Gestalt(g)),
-                                        Abstract::Var(Var::Void(_)),
-                                    ) => {
-                                        let file: Vec<u8> =
-                                            match fs::read_to_string(string_from_utf8!(g.to_vec()))
-                                            {
-                                                Ok(s) => s.into_bytes(),
-                                                Err(_) => return_error!("Error in opening file"),
-                                            };
+                                    clear_and_progress!();
 
-                                        clear_and_progress!();
+                                    stack.push_front(Abstract::Var(result));
+                                }
 
-                                        stack.push_front(Abstract::Var(Var::Gestalt(file)));
+                                //Sending a value along a channel
+                                b't' => {
+                                    let (channel, v) = match (unpack_stack!(1), unpack_stack!(0)) {
+                                        (Abstract::Var(channel), Abstract::Var(v)) => (channel, v),
+                                        _ => return_error!("Invalid operand types"),
+                                    };
+
+                                    let sent = match handle(channel) {
+                                        Some(id) => interpreter.threads().send(id, v.clone()),
+                                        None => return_error!("Channel not found"),
+                                    };
+                                    if let Err(error) = sent {
+                                        return_error!(error);
                                     }
+                                    let result = Var::void();
 
-                                    (
-                                        Abstract::Var(Var::Gestalt(ga)),
-                                        Abstract::Var(Var::Gestalt(gb)),
-                                    ) => {
-                                        //If the file does not exist at the specified path, create one, and open it up either way.
-                                        //Read the contents and store them, then write the new contents to the file.
-                                        //If the file didnt' exist before, return a Void, if not, return the old contents.
-
-                                        let path = string_from_utf8!(ga.to_vec());
-                                        let exists = Path::new(&path).exists();
-
-                                        let mut file;
-
-                                        if !exists {
-                                            match fs::File::create(path.clone()) {
-                                                Ok(f) => f,
-                                                Err(_) => {
-                                                    return_error!("Error in creating file")
-                                                }
-                                            };
-                                        }
+                                    interpreter.hook.applied(&step!(), a, channel, v, &result);
 
-                                        file = match fs::File::open(&path) {
-                                            Ok(f) => f,
-                                            Err(_) => return_error!("Error in opening file"),
-                                        };
+                                    clear_and_progress!();
 
-                                        let mut contents = String::new();
+                                    stack.push_front(Abstract::Var(result));
+                                }
 
-                                        match file.read_to_string(&mut contents) {
-                                            Ok(_) => (),
-                                            Err(_) => {
-                                                return_error!("Error reading file to string")
+                                //Waiting for the next value sent along a channel, for at most b seconds (or forever given
+                                //a Void), giving a Void if nothing was sent by then
+                                b'w' => {
+                                    let (channel, secondary) =
+                                        match (unpack_stack!(1), unpack_stack!(0)) {
+                                            (Abstract::Var(channel), Abstract::Var(secondary)) => {
+                                                (channel, secondary)
                                             }
+                                            _ => return_error!("Invalid operand types"),
+                                        };
+                                    //A timeout too long to be measured is the same as none at all
+                                    let timeout = match secondary {
+                                        Var::Void(_) => None,
+                                        Var::Linear(seconds) if *seconds >= 0.0 => {
+                                            Duration::try_from_secs_f64(*seconds).ok()
                                         }
+                                        _ => return_error!("Invalid operand types"),
+                                    };
+
+                                    let received = match handle(channel) {
+                                        Some(id) => interpreter.threads().receive(
+                                            id,
+                                            timeout,
+                                            &interpreter.cancel_token(),
+                                        ),
+                                        None => return_error!("Channel not found"),
+                                    };
+                                    let result = match received {
+                                        Ok(received) => received.unwrap_or_else(Var::void),
+                                        Err(error) => return_error!(error),
+                                    };
+
+                                    interpreter.hook.applied(
+                                        &step!(),
+                                        a,
+                                        channel,
+                                        secondary,
+                                        &result,
+                                    );
+
+                                    clear_and_progress!();
 
-                                        fs::write(
-                                            string_from_utf8!(ga.to_vec()),
-                                            string_from_utf8!(gb.to_vec()),
-                                        )
-                                        .expect(
-                                            &("Unable to write file at ".to_string()
-                                                + &on.to_string()),
-                                        );
+                                    stack.push_front(Abstract::Var(result));
+                                }
 
-                                        clear_and_progress!();
+                                //Mapping in parallel, which evaluates the jump or Gestalt of code a with every element of b as
+                                //its input, spread across threads, giving a Set of what each returned in the same order
+                                b'm' => {
+                                    let (evaluated, elements) =
+                                        match (unpack_stack!(1), unpack_stack!(0)) {
+                                            (
+                                                Abstract::Var(evaluated),
+                                                Abstract::Var(Var::Set(elements)),
+                                            ) => (evaluated, elements),
+                                            _ => return_error!("Invalid operand types"),
+                                        };
 
-                                        stack.push_front(Abstract::Var(if exists {
-                                            Var::Gestalt(contents.into())
-                                        } else {
-                                            Var::void()
-                                        }));
+                                    //Every evaluation is made before any start, so a bad jump throws straight away
+                                    let mut frames = Vec::with_capacity(elements.len());
+                                    for element in elements {
+                                        match callable_frame(
+                                            code, *start, origin, evaluated, element, 0,
+                                        ) {
+                                            Ok(frame) => frames.push(frame),
+                                            Err(error) => return_error!(error),
+                                        }
                                     }
 
-                                    _ => return_error!("Invalid operand types"),
-                                },
+                                    let worker = interpreter.spawned();
+                                    let mapped = parallel_map(&frames, |frame| {
+                                        let mut task = Task {
+                                            frames: vec![frame.clone()],
+                                            scope: Scope::default(),
+                                            thrown_at: None,
+                                        };
+                                        task.finish(&mut worker.spawned())
+                                    });
 
-                                //Set & gestalt indexing, macro can't cover these subtypeless sets so its got its own special thingy
-                                b'`' => match (unpack_stack!(1), unpack_stack!(0)) {
-                                    (Abstract::Var(Var::Set(s)), Abstract::Var(Var::Linear(l))) => {
-                                        let element = Abstract::Var(
-                                            match s.get(*l as i64 as usize) {
-                                                Some(i) => i,
-                                                _ => {
-                                                    return_error!(
-                                                        "Could not get index ".to_string()
-                                                            + &format!("{}", *l as i64 as usize)
-                                                            + " from Set"
-                                                    )
-                                                }
-                                            }
-                                            .clone(),
-                                        );
+                                    let result = match mapped {
+                                        Ok(values) => Var::Set(values),
+                                        Err((index, msg)) => {
+                                            return_error!(format!("{} (In element {})", msg, index))
+                                        }
+                                    };
+
+                                    interpreter.hook.applied(
+                                        &step!(),
+                                        a,
+                                        evaluated,
+                                        &Var::Set(elements.clone()),
+                                        &result,
+                                    );
 
-                                        clear_and_progress!();
+                                    clear_and_progress!();
 
-                                        stack.push_front(element);
-                                    }
+                                    stack.push_front(Abstract::Var(result));
+                                }
 
-                                    (
-                                        Abstract::Var(Var::Gestalt(g)),
-                                        Abstract::Var(Var::Linear(l)),
-                                    ) => {
-                                        let char = g[*l as i64 as usize];
+                                //CONSOLE
 
-                                        clear_and_progress!();
+                                //Printing to standard output and standard error, Gestalts are written as they are
+                                b'p' | b'e' => {
+                                    let text = match unpack_stack!(1) {
+                                        Abstract::Var(Var::Gestalt(g)) => g.clone(),
+                                        Abstract::Var(v) => v.represent().into_bytes(),
+                                        _ => return_error!("Invalid operand types"),
+                                    };
 
-                                        stack.push_front(Abstract::Var(Var::Gestalt(vec![char])));
+                                    let written = if a == b'p' {
+                                        interpreter.io().write_out(&text)
+                                    } else {
+                                        interpreter.io().write_err(&text)
+                                    };
+
+                                    if written.is_err() {
+                                        return_error!("Error in writing output");
                                     }
 
-                                    //Special modulus functionality
-                                    (
-                                        Abstract::Var(Var::Linear(a)),
-                                        Abstract::Var(Var::Linear(b)),
-                                    ) => {
-                                        let result = a % b;
+                                    clear_and_progress!();
+                                }
 
-                                        clear_and_progress!();
+                                //Reading a line of input, giving a Void once the input has ended
+                                b'l' => {
+                                    let (primary, secondary) =
+                                        match (unpack_stack!(1), unpack_stack!(0)) {
+                                            (Abstract::Var(primary), Abstract::Var(secondary)) => {
+                                                (primary, secondary)
+                                            }
+                                            _ => return_error!("Invalid operand types"),
+                                        };
 
-                                        stack.push_front(Abstract::Var(Var::Linear(result)));
-                                    }
+                                    let line = match interpreter.io().read_line() {
+                                        Ok(Some(line)) => Var::Gestalt(line),
+                                        Ok(None) => Var::void(),
+                                        Err(_) => return_error!("Error in reading input"),
+                                    };
+
+                                    interpreter.hook.applied(
+                                        &step!(),
+                                        a,
+                                        primary,
+                                        secondary,
+                                        &line,
+                                    );
 
-                                    _ => return_error!("Invalid types for operator"),
-                                },
+                                    clear_and_progress!();
 
-                                //Conditional, everything should've already been handled by the opening bracket.
-                                //If this point is reached, then
-                                b'?' => {}
+                                    stack.push_front(Abstract::Var(line));
+                                }
+
+                                //Reading all the remaining input
+                                b'r' => {
+                                    let (primary, secondary) =
+                                        match (unpack_stack!(1), unpack_stack!(0)) {
+                                            (Abstract::Var(primary), Abstract::Var(secondary)) => {
+                                                (primary, secondary)
+                                            }
+                                            _ => return_error!("Invalid operand types"),
+                                        };
+
+                                    let all = match interpreter.io().read_all() {
+                                        Ok(all) => Var::Gestalt(all),
+                                        Err(_) => return_error!("Error in reading input"),
+                                    };
+
+                                    interpreter
+                                        .hook
+                                        .applied(&step!(), a, primary, secondary, &all);
+
+                                    clear_and_progress!();
 
-                                //Invalid operator
-                                _ => return_error!("Invalid operator"),
+                                    stack.push_front(Abstract::Var(all));
+                                }
+
+                                //Conditional, everything should've already been handled by the opening bracket.
+                                //If this point is reached, the conditional never got its opening bracket.
+                                b'?' => return_error!("Conditional given no code to run"),
+
+                                //Everything else only needs the values of its arguments
+                                operator => {
+                                    let (a, b) = match (unpack_stack!(1), unpack_stack!(0)) {
+                                        (Abstract::Var(a), Abstract::Var(b)) => (a, b),
+                                        _ => return_error!("Invalid operand types"),
+                                    };
+
+                                    //Errors are thrown before clearing, so the debug info still shows the operands
+                                    match operate(operator, a, b) {
+                                        Ok(result) => {
+                                            interpreter.hook.applied(
+                                                &step!(),
+                                                operator,
+                                                a,
+                                                b,
+                                                &result,
+                                            );
+
+                                            clear_and_progress!();
+
+                                            stack.push_front(Abstract::Var(result));
+                                        }
+                                        Err(error) => return_error!(error),
+                                    }
+                                }
                             }
                         }
 
@@ -831,11 +1147,11 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
 
             //Terminator character, immediately matches top of stack to var and returns it, if its not a var then it returns void.
             b';' => {
-                return match stack.pop_front() {
+                return Progress::Done(match stack.pop_front() {
                     Some(Abstract::Var(v)) => Ok(v),
 
                     _ => Ok(Var::void()),
-                }
+                })
             }
 
             //Anything else (valid) should be a normal operator, so they just get appended.
@@ -848,3 +1164,45 @@ pub fn evaluate(program: &[u8], input: &Var) -> Evaluation {
         }
     }
 }
+
+//The frame evaluating a jump (from where it is within the code) or a Gestalt of code, given its input
+fn callable_frame(
+    code: &Arc<[u8]>,
+    start: usize,
+    origin: Option<usize>,
+    callable: &Var,
+    input: &Var,
+    depth: usize,
+) -> Result<Frame, &'static str> {
+    let (code, start, origin) = match callable {
+        Var::Linear(jmp) => {
+            if !(*jmp >= 0.0 && *jmp <= (code.len() - start) as f64) {
+                return Err("Jump out of bounds");
+            }
+            let jmp = *jmp as usize;
+            (code.clone(), start + jmp, origin.map(|origin| origin + jmp))
+        }
+        Var::Gestalt(g) => (Arc::from(g.as_slice()), 0, None),
+        _ => return Err("Invalid operand types"),
+    };
+
+    Ok(Frame {
+        code,
+        start,
+        input: input.clone(),
+        scope: Scope::default(),
+        stack: VecDeque::new(),
+        on: 0,
+        origin,
+        depth,
+        returned: None,
+    })
+}
+
+//The id of a thread or channel, kept in a Linear
+fn handle(var: &Var) -> Option<usize> {
+    match var {
+        Var::Linear(id) if *id >= 0.0 && id.fract() == 0.0 => Some(*id as usize),
+        _ => None,
+    }
+}
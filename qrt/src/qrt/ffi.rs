@@ -0,0 +1,203 @@
+use super::{
+    evaluate::Task, interpreter::Interpreter, json::stringify, structs::*, syntax::line_column,
+};
+
+use std::{
+    ffi::{c_char, CStr, CString},
+    ptr, slice,
+};
+
+//The C API, built into the shared library and declared in ffi/qrt.h.
+//Everything given out is owned by the caller, and freed with the matching free function (never with C's free).
+//Functions are documented with ///, since those comments are copied into the header along with each one's safety
+//contract: what a caller has to make sure of, which Rust can't check for them.
+
+//An interpreter attached to the real console
+pub struct QrtInterpreter(Interpreter);
+
+//The value an evaluation gave, or the error it threw
+pub struct QrtResult {
+    evaluation: Evaluation,
+    error: Option<CString>,
+    position: usize, //Where in the source the error was thrown, even within a jump (whose errors are placed within it)
+    line: usize,
+}
+
+/// Creates an interpreter, to be freed with qrt_interpreter_free
+#[no_mangle]
+pub extern "C" fn qrt_interpreter_new() -> *mut QrtInterpreter {
+    Box::into_raw(Box::new(QrtInterpreter(Interpreter::new())))
+}
+
+/// Frees an interpreter, doing nothing given NULL
+///
+/// # Safety
+/// The interpreter must be NULL or one given by qrt_interpreter_new, which hasn't already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn qrt_interpreter_free(interpreter: *mut QrtInterpreter) {
+    if !interpreter.is_null() {
+        drop(Box::from_raw(interpreter));
+    }
+}
+
+/// Evaluates length bytes of source, with input written as a QRT literal (or NULL for a Void).
+/// Gives a result to be freed with qrt_result_free, which holds the error if the input literal was invalid or the
+/// interpreter was NULL.
+///
+/// # Safety
+/// The interpreter must be NULL or one given by qrt_interpreter_new that hasn't been freed, and isn't being used by
+/// another thread. The source must be NULL or point to at least length bytes, and the input must be NULL or a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn qrt_evaluate(
+    interpreter: *mut QrtInterpreter,
+    source: *const u8,
+    length: usize,
+    input: *const c_char,
+) -> *mut QrtResult {
+    let program = if source.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(source, length)
+    };
+
+    let input = match input.as_ref() {
+        None => Ok(Var::void()),
+        Some(_) => Var::from_qrt_literal(CStr::from_ptr(input).to_bytes())
+            .map_err(|error| format!("Invalid input: {}", error)),
+    };
+
+    let (evaluation, position) = match (interpreter.as_mut(), input) {
+        (None, _) => (failure("No interpreter given".to_string()), 0),
+        (Some(_), Err(error)) => (failure(error), 0),
+        (Some(interpreter), Ok(input)) => {
+            let mut task = Task::new(program, &input);
+            let evaluation = task.finish(&mut interpreter.0);
+            let position = match &evaluation {
+                Ok(_) => 0,
+                Err((_, on, ..)) => task.thrown_at().unwrap_or(*on),
+            };
+            (evaluation, position)
+        }
+    };
+    let line = match &evaluation {
+        Ok(_) => 0,
+        Err(_) => line_column(program, position).0,
+    };
+
+    //NUL bytes can't be within a C string, so any in the message are written out as \0 instead
+    let error = match &evaluation {
+        Ok(_) => None,
+        Err((msg, ..)) => Some(CString::new(msg.replace('\0', "\\0")).unwrap_or_default()),
+    };
+
+    Box::into_raw(Box::new(QrtResult {
+        evaluation,
+        error,
+        position,
+        line,
+    }))
+}
+
+/// Whether the evaluation gave a value rather than throwing an error
+///
+/// # Safety
+/// The result must be one given by qrt_evaluate, which hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn qrt_result_ok(result: *const QrtResult) -> bool {
+    (*result).evaluation.is_ok()
+}
+
+/// The value as a QRT literal, to be freed with qrt_string_free.
+/// NULL if evaluation threw an error, or the value is a Gestalt holding a NUL byte.
+///
+/// # Safety
+/// The result must be one given by qrt_evaluate, which hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn qrt_result_literal(result: *const QrtResult) -> *mut c_char {
+    match &(*result).evaluation {
+        Ok(var) => to_c_string(var.to_qrt_literal().into_bytes()),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// The value as JSON, with pair Sets written as objects if asked to, to be freed with qrt_string_free.
+/// NULL if evaluation threw an error, or the value can't be written as JSON.
+///
+/// # Safety
+/// The result must be one given by qrt_evaluate, which hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn qrt_result_json(result: *const QrtResult, objects: bool) -> *mut c_char {
+    match &(*result).evaluation {
+        Ok(var) => match stringify(var, objects) {
+            Ok(json) => to_c_string(json),
+            Err(_) => ptr::null_mut(),
+        },
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// The error message, owned by the result (so freed along with it), or NULL if evaluation gave a value
+///
+/// # Safety
+/// The result must be one given by qrt_evaluate, which hasn't been freed. The message can't be used once it has.
+#[no_mangle]
+pub unsafe extern "C" fn qrt_result_error(result: *const QrtResult) -> *const c_char {
+    match &(*result).error {
+        Some(error) => error.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// The position in the source (counted in bytes) the error was thrown at, even within a jump, or 0 if evaluation gave a
+/// value
+///
+/// # Safety
+/// The result must be one given by qrt_evaluate, which hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn qrt_result_error_position(result: *const QrtResult) -> usize {
+    (*result).position
+}
+
+/// The line (counted from 0) the error was thrown on, or 0 if evaluation gave a value
+///
+/// # Safety
+/// The result must be one given by qrt_evaluate, which hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn qrt_result_error_line(result: *const QrtResult) -> usize {
+    (*result).line
+}
+
+/// Frees a result, doing nothing given NULL
+///
+/// # Safety
+/// The result must be NULL or one given by qrt_evaluate, which hasn't already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn qrt_result_free(result: *mut QrtResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
+
+/// Frees a string given by qrt_result_literal or qrt_result_json, doing nothing given NULL
+///
+/// # Safety
+/// The string must be NULL or one given by qrt_result_literal or qrt_result_json, which hasn't already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn qrt_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+fn to_c_string(bytes: Vec<u8>) -> *mut c_char {
+    match CString::new(bytes) {
+        Ok(string) => string.into_raw(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+//An error from before evaluation could start, thrown at the very start of the source
+fn failure(msg: String) -> Evaluation {
+    Err((msg, 0, 0, Default::default(), Default::default()))
+}
@@ -0,0 +1,181 @@
+use super::structs::*;
+
+use std::{fs, io::Write, path::Path, time::UNIX_EPOCH};
+
+//Everything QRT code can do with a file or directory through @
+pub enum FileOperation {
+    Read,
+    Write(Vec<u8>),
+    ReadBytes,
+    WriteBytes(Vec<u8>),
+    Append(Vec<u8>),
+    Delete,
+    Exists,
+    List,
+    CreateDirectory,
+    Metadata,
+}
+
+impl FileOperation {
+    //Reads the secondary argument of @ as an operation. Voids read and Gestalts write, like always,
+    //while everything else is given as a Set of the operation's name and (for appending) its contents.
+    pub fn from_var(var: &Var) -> Result<FileOperation, &'static str> {
+        match var {
+            Var::Void(_) => Ok(FileOperation::Read),
+
+            Var::Gestalt(g) => Ok(FileOperation::Write(g.clone())),
+
+            Var::Set(set) => match set.as_slice() {
+                [Var::Gestalt(name)] => match name.as_slice() {
+                    b"readbytes" => Ok(FileOperation::ReadBytes),
+                    b"delete" => Ok(FileOperation::Delete),
+                    b"exists" => Ok(FileOperation::Exists),
+                    b"list" => Ok(FileOperation::List),
+                    b"mkdir" => Ok(FileOperation::CreateDirectory),
+                    b"metadata" => Ok(FileOperation::Metadata),
+                    _ => Err("Unknown file operation"),
+                },
+
+                [Var::Gestalt(name), Var::Gestalt(contents)] if name == b"append" => {
+                    Ok(FileOperation::Append(contents.clone()))
+                }
+
+                [Var::Gestalt(name), Var::Gestalt(contents)] if name == b"writebytes" => {
+                    Ok(FileOperation::WriteBytes(contents.clone()))
+                }
+
+                _ => Err("Unknown file operation"),
+            },
+
+            _ => Err("Invalid operand types"),
+        }
+    }
+}
+
+//Every file access QRT makes goes through here, so anything restricting where QRT may reach only has to be done once.
+pub fn access_file(path: &[u8], operation: FileOperation) -> Result<Var, &'static str> {
+    let path = match core::str::from_utf8(path) {
+        Ok(p) => Path::new(p),
+        Err(_) => return Err("Invalid Gestalt chars"),
+    };
+
+    match operation {
+        FileOperation::Read => read(path, true),
+
+        FileOperation::ReadBytes => read(path, false),
+
+        FileOperation::Write(contents) => write(path, contents, true),
+
+        FileOperation::WriteBytes(contents) => write(path, contents, false),
+
+        FileOperation::Append(contents) => {
+            let appended = fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(&contents));
+
+            match appended {
+                Ok(_) => Ok(Var::void()),
+                Err(_) => Err("Error in appending to file"),
+            }
+        }
+
+        FileOperation::Delete => {
+            let deleted = if path.is_dir() {
+                fs::remove_dir(path)
+            } else {
+                fs::remove_file(path)
+            };
+
+            match deleted {
+                Ok(_) => Ok(Var::void()),
+                Err(_) => Err("Error in deleting file"),
+            }
+        }
+
+        FileOperation::Exists => Ok(Var::Linear(if path.exists() { 1.0 } else { 0.0 })),
+
+        //Names are sorted so listings don't depend on the platform's order
+        FileOperation::List => {
+            let entries = match fs::read_dir(path) {
+                Ok(entries) => entries,
+                Err(_) => return Err("Error in listing directory"),
+            };
+
+            let mut names: Vec<Vec<u8>> = Vec::new();
+
+            for entry in entries {
+                match entry {
+                    Ok(entry) => {
+                        names.push(entry.file_name().to_string_lossy().as_bytes().to_vec())
+                    }
+                    Err(_) => return Err("Error in listing directory"),
+                }
+            }
+
+            names.sort();
+
+            Ok(Var::Set(names.into_iter().map(Var::Gestalt).collect()))
+        }
+
+        FileOperation::CreateDirectory => match fs::create_dir_all(path) {
+            Ok(_) => Ok(Var::void()),
+            Err(_) => Err("Error in creating directory"),
+        },
+
+        //Gives [size in bytes, modified time in seconds since the unix epoch]
+        FileOperation::Metadata => {
+            let metadata = match fs::metadata(path) {
+                Ok(metadata) => metadata,
+                Err(_) => return Err("Error in reading file metadata"),
+            };
+
+            let modified = match metadata
+                .modified()
+                .map(|time| time.duration_since(UNIX_EPOCH))
+            {
+                Ok(Ok(duration)) => duration.as_secs_f64(),
+                _ => return Err("Error in reading file metadata"),
+            };
+
+            Ok(Var::Set(vec![
+                Var::Linear(metadata.len() as f64),
+                Var::Linear(modified),
+            ]))
+        }
+    }
+}
+
+//Reads a whole file, in text mode the file has to be valid UTF-8 while in byte mode anything goes
+fn read(path: &Path, text: bool) -> Result<Var, &'static str> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(_) => return Err("Error in opening file"),
+    };
+
+    if text && core::str::from_utf8(&contents).is_err() {
+        return Err("File is not valid text, try reading its bytes");
+    }
+
+    Ok(Var::Gestalt(contents))
+}
+
+//Replaces a whole file, creating it if it does not exist at the specified path.
+//If the file didn't exist before, return a Void, if not, return the old contents.
+fn write(path: &Path, contents: Vec<u8>, text: bool) -> Result<Var, &'static str> {
+    let old = if path.exists() {
+        read(path, text)?
+    } else {
+        Var::void()
+    };
+
+    if text && core::str::from_utf8(&contents).is_err() {
+        return Err("Invalid Gestalt chars");
+    }
+
+    match fs::write(path, contents) {
+        Ok(_) => Ok(old),
+        Err(_) => Err("Error in writing file"),
+    }
+}
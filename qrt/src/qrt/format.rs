@@ -0,0 +1,185 @@
+use super::syntax::*;
+
+//Re-emits QRT source in one consistent layout: one item per line, secondary arguments of loops, jump definitions
+//and conditionals (or anything holding more than one item) indented on their own lines, Sets spaced out as
+//[a, b, c], and every comment kept. Literals, names and comments are copied from the source as they were written.
+pub fn format(program: &[u8]) -> Result<Vec<u8>, SyntaxError> {
+    let block = parse(program)?;
+
+    let mut formatter = Formatter {
+        program,
+        out: Vec::new(),
+    };
+    formatter.items(&block, 0);
+
+    Ok(formatter.out)
+}
+
+const INDENT: &[u8] = b"    ";
+
+struct Formatter<'a> {
+    program: &'a [u8],
+    out: Vec<u8>,
+}
+impl Formatter<'_> {
+    fn indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.out.extend_from_slice(INDENT);
+        }
+    }
+
+    //Writes each item on its own line
+    fn items(&mut self, block: &Block, depth: usize) {
+        for item in &block.items {
+            if item.blank_before {
+                self.out.push(b'\n');
+            }
+
+            for comment in &item.comments {
+                self.indent(depth);
+                self.out.extend_from_slice(&comment.text);
+                self.out.push(b'\n');
+            }
+
+            self.indent(depth);
+            if let Some(node) = &item.node {
+                self.node(node, depth);
+            }
+            if item.terminated {
+                self.out.push(b';');
+            }
+            if let Some(comment) = &item.trailing {
+                self.out.push(b' ');
+                self.out.extend_from_slice(&comment.text);
+            }
+            self.out.push(b'\n');
+        }
+
+        for comment in &block.comments {
+            self.indent(depth);
+            self.out.extend_from_slice(&comment.text);
+            self.out.push(b'\n');
+        }
+    }
+
+    //Writes comments inline, before an expression that isn't on its own line
+    fn inline_comments(&mut self, comments: &[Comment]) {
+        for comment in comments {
+            self.out.extend_from_slice(&comment.text);
+            self.out.push(b' ');
+        }
+    }
+
+    //Writes a node from wherever the output currently is, with anything it puts on new lines at the given depth
+    fn node(&mut self, node: &Node, depth: usize) {
+        match &node.expression {
+            Expression::Set(elements, comments) if set_is_multiline(elements, comments) => {
+                self.out.extend_from_slice(b"[\n");
+
+                for (i, element) in elements.iter().enumerate() {
+                    for comment in &element.comments {
+                        self.indent(depth + 1);
+                        self.out.extend_from_slice(&comment.text);
+                        self.out.push(b'\n');
+                    }
+
+                    self.indent(depth + 1);
+                    self.node(element, depth + 1);
+                    if i + 1 < elements.len() {
+                        self.out.push(b',');
+                    }
+                    self.out.push(b'\n');
+                }
+
+                for comment in comments {
+                    self.indent(depth + 1);
+                    self.out.extend_from_slice(&comment.text);
+                    self.out.push(b'\n');
+                }
+
+                self.indent(depth);
+                self.out.push(b']');
+            }
+
+            Expression::Set(elements, _) => {
+                self.out.push(b'[');
+
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        self.out.extend_from_slice(b", ");
+                    }
+                    self.node(element, depth);
+                }
+
+                self.out.push(b']');
+            }
+
+            Expression::Named(operator, name, block) => {
+                self.out.push(*operator);
+                self.out.extend_from_slice(name);
+                self.secondary(*operator, block, depth);
+            }
+
+            Expression::Operation(operator, primary, block) => {
+                self.out.push(*operator);
+                self.inline_comments(&primary.comments);
+                self.node(primary, depth);
+                self.secondary(*operator, block, depth);
+            }
+
+            //Literals and references are written exactly as they were
+            _ => self.out.extend_from_slice(&self.program[node.span.clone()]),
+        }
+    }
+
+    fn secondary(&mut self, operator: u8, block: &Block, depth: usize) {
+        if block.items.is_empty() && block.comments.is_empty() {
+            self.out.extend_from_slice(b"{}");
+        } else if block_is_multiline(operator, block) {
+            self.out.extend_from_slice(b"{\n");
+            self.items(block, depth + 1);
+            self.indent(depth);
+            self.out.push(b'}');
+        } else if let Some(node) = &block.items[0].node {
+            self.out.push(b'{');
+            self.node(node, depth);
+            self.out.push(b'}');
+        }
+    }
+}
+
+//Secondary arguments that are code to run, rather than a value, always go on their own lines
+fn block_is_multiline(operator: u8, block: &Block) -> bool {
+    matches!(operator, b'~' | b':' | b'?')
+        || !block.comments.is_empty()
+        || block.items.len() != 1
+        || block.items.iter().any(|item| {
+            item.terminated
+                || item.trailing.is_some()
+                || !item.comments.is_empty()
+                || item.node.as_ref().is_some_and(is_multiline)
+        })
+}
+
+fn set_is_multiline(elements: &[Node], comments: &[Comment]) -> bool {
+    !comments.is_empty()
+        || elements
+            .iter()
+            .any(|element| !element.comments.is_empty() || is_multiline(element))
+}
+
+fn is_multiline(node: &Node) -> bool {
+    match &node.expression {
+        Expression::Set(elements, comments) => set_is_multiline(elements, comments),
+        Expression::Named(operator, _, block) => {
+            !(block.items.is_empty() && block.comments.is_empty())
+                && block_is_multiline(*operator, block)
+        }
+        Expression::Operation(operator, primary, block) => {
+            is_multiline(primary)
+                || !(block.items.is_empty() && block.comments.is_empty())
+                    && block_is_multiline(*operator, block)
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,377 @@
+use super::{
+    evaluate::evaluate_from,
+    interpreter::{Hook, Interpreter, Step},
+    io::BufferIo,
+    structs::*,
+    syntax::{line_column, parse, Expression},
+};
+use std::{
+    collections::HashMap,
+    fs, io, mem,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+//Golden file tests: every .qrt file is run, and what it does is checked against files kept next to it.
+//  NAME.in is given to the program as its console input
+//  NAME.out is everything the program is expected to print to standard output
+//  NAME.err is the error the program is expected to throw
+//A missing .out or .err means no output or no error is expected. Comments at the very top of the program can hold
+//annotations, \input: LITERAL\ giving the program's input ($), and \result: LITERAL\ being the value it should give.
+//Programs can also hold unit tests, jumps named test_ something, which are each run on their own.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Passed,
+    Failed(Vec<String>), //Every way the test failed, with diffs for output that didn't match
+    Updated,             //Its expectations were rewritten to match what it did
+}
+
+//A jump named test_ something, which is run on its own as a unit test
+pub struct TestJump {
+    pub name: String,
+    pub span: Range<usize>, //Of the operator and name of its definition
+    start: usize,           //Where its code starts
+    end: usize,             //Where its closing bracket is
+}
+
+//An annotation from the top of a program, with the span of the text after its colon
+struct Annotation {
+    name: String,
+    value: Range<usize>,
+}
+
+//Finds every .qrt file within the given path (or just the path itself if it is one), in order
+pub fn discover(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut found = Vec::new();
+
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() {
+            found.extend(discover(&entry)?);
+        } else if entry
+            .extension()
+            .is_some_and(|extension| extension == "qrt")
+        {
+            found.push(entry);
+        }
+    }
+
+    Ok(found)
+}
+
+//Runs the test at the given path. With update, anything that didn't match is written back as what's expected instead.
+pub fn run(path: &Path, update: bool) -> io::Result<Outcome> {
+    let program = fs::read(path)?;
+    let annotations = annotations(&program);
+    let mut failures = Vec::new();
+
+    let annotated = |name: &str| {
+        annotations
+            .iter()
+            .find(|annotation| annotation.name == name)
+    };
+
+    let input = match annotated("input") {
+        Some(annotation) => match Var::from_qrt_literal(&program[annotation.value.clone()]) {
+            Ok(input) => input,
+            Err(error) => {
+                return Ok(Outcome::Failed(vec![format!(
+                    "Invalid input annotation: {}",
+                    error
+                )]))
+            }
+        },
+        None => Var::void(),
+    };
+
+    let stdin = read_sidecar(path, "in")?.unwrap_or_default();
+    let mut interpreter = Interpreter::with_io(BufferIo::new(&stdin));
+    let result = interpreter
+        .evaluate(&program, &input)
+        .map_err(|(msg, ..)| msg);
+    let output = mem::take(&mut interpreter.io().output);
+
+    //Result
+    let mut rewritten = program.clone();
+    if let Some(annotation) = annotated("result") {
+        let expected = Var::from_qrt_literal(&program[annotation.value.clone()]);
+
+        match (&result, expected) {
+            (Ok(result), Ok(expected)) if *result == expected => (),
+            (Ok(result), _) if update => {
+                rewritten.splice(
+                    annotation.value.clone(),
+                    format!(" {}", result.to_qrt_literal()).into_bytes(),
+                );
+            }
+            (Ok(result), Ok(expected)) => failures.push(format!(
+                "Result differs: expected {}, got {}",
+                expected.to_qrt_literal(),
+                result.to_qrt_literal()
+            )),
+            (Ok(_), Err(error)) => failures.push(format!("Invalid result annotation: {}", error)),
+            (Err(_), _) => (),
+        }
+    }
+
+    //Output
+    let expected_output = read_sidecar(path, "out")?.unwrap_or_default();
+    if output != expected_output {
+        failures.push(format!(
+            "Output differs:\n{}",
+            diff(
+                &String::from_utf8_lossy(&expected_output),
+                &String::from_utf8_lossy(&output)
+            )
+        ));
+    }
+
+    //Error
+    //The error file's trailing newline isn't part of the error, since editors like to add one
+    let expected_error = read_sidecar(path, "err")?.map(|error| {
+        String::from_utf8_lossy(&error)
+            .strip_suffix('\n')
+            .map(str::to_string)
+            .unwrap_or_else(|| String::from_utf8_lossy(&error).into_owned())
+    });
+    let error = result.as_ref().err();
+    match (error, &expected_error) {
+        (Some(error), Some(expected)) if error != expected => {
+            failures.push(format!("Error differs:\n{}", diff(expected, error)))
+        }
+        (Some(error), None) => failures.push(format!("Unexpected error: {}", error)),
+        (None, Some(expected)) => failures.push(format!("Expected an error: {}", expected)),
+        _ => (),
+    }
+
+    if !update {
+        return Ok(if failures.is_empty() {
+            Outcome::Passed
+        } else {
+            Outcome::Failed(failures)
+        });
+    }
+
+    if failures.is_empty() && rewritten == program {
+        return Ok(Outcome::Passed);
+    }
+
+    if rewritten != program {
+        fs::write(path, rewritten)?;
+    }
+    write_sidecar(path, "out", &output)?;
+    write_sidecar(
+        path,
+        "err",
+        &error
+            .map(|error| error.clone() + "\n")
+            .unwrap_or_default()
+            .into_bytes(),
+    )?;
+
+    Ok(Outcome::Updated)
+}
+
+//Every test jump defined in the program (outside of other jumps), in the order they're written
+pub fn test_jumps(program: &[u8]) -> Vec<TestJump> {
+    let block = match parse(program) {
+        Ok(block) => block,
+        Err(_) => return Vec::new(),
+    };
+
+    block
+        .definitions()
+        .into_iter()
+        .filter_map(|(_, node)| match &node.expression {
+            Expression::Named(b':', name, body) if name.starts_with(b"test_") => Some(TestJump {
+                name: String::from_utf8_lossy(name).into_owned(),
+                span: node.head(),
+                start: body.span.start + 1,
+                end: body.span.end - 1,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+//Runs a test jump on its own, with a Void as its input, which passes unless it throws an error (like a failed assertion).
+//Unlike a jump evaluated with !, it can use the jumps defined outside of it (outside of other jumps), so it can test them.
+//A failure is given with the line and character the error was thrown on.
+pub fn run_test(path: &Path, program: &[u8], test: &TestJump) -> Outcome {
+    let mut interpreter = Interpreter::with_io(BufferIo::default()).with_hook(Locator {
+        on: test.start,
+        end: test.end,
+        finished: false,
+    });
+
+    //The whole program is evaluated from the start of the test, so the jumps are where they'd be for the program itself
+    let mut jumps = HashMap::new();
+    if let Ok(block) = parse(program) {
+        for (name, node) in block.definitions() {
+            if let Expression::Named(b':', _, body) = &node.expression {
+                jumps.insert(
+                    String::from_utf8_lossy(&name).into_owned(),
+                    Var::Linear((body.span.start + 1) as f64),
+                );
+            }
+        }
+    }
+
+    match evaluate_from(
+        &mut interpreter,
+        program,
+        &Var::void(),
+        jumps,
+        test.start,
+        Some(0),
+        0,
+    ) {
+        Ok(_) => Outcome::Passed,
+        //Reaching its closing bracket means the test ran all the way through without returning
+        Err(_) if interpreter.hook.finished => Outcome::Passed,
+        Err((msg, ..)) => {
+            let (line, column) = line_column(program, interpreter.hook.on);
+            Outcome::Failed(vec![format!(
+                "{}:{}:{}: {}",
+                path.display(),
+                line + 1,
+                column + 1,
+                msg
+            )])
+        }
+    }
+}
+
+//Keeps the last place in the program that evaluation reached, which is where an error would have been thrown.
+//It stops evaluation once the test reaches its own closing bracket, since the rest of the program isn't part of it.
+struct Locator {
+    on: usize,
+    end: usize,
+    finished: bool,
+}
+
+impl Hook for Locator {
+    fn step(&mut self, step: &Step) -> Result<(), String> {
+        if let Some(origin) = step.origin {
+            self.on = origin + step.on;
+        }
+
+        if step.depth == 0 && self.on == self.end {
+            self.finished = true;
+            return Err("Test finished".to_string());
+        }
+        Ok(())
+    }
+}
+
+//The annotations in the comments at the top of a program, before any code
+fn annotations(program: &[u8]) -> Vec<Annotation> {
+    let mut annotations = Vec::new();
+    let mut on = 0;
+
+    loop {
+        while program.get(on).is_some_and(u8::is_ascii_whitespace) {
+            on += 1;
+        }
+
+        if program.get(on) != Some(&b'\\') {
+            return annotations;
+        }
+
+        let start = on + 1;
+        let end = match program[start..].iter().position(|c| *c == b'\\') {
+            Some(length) => start + length,
+            None => return annotations,
+        };
+
+        if let Some(colon) = program[start..end].iter().position(|c| *c == b':') {
+            annotations.push(Annotation {
+                name: String::from_utf8_lossy(&program[start..start + colon])
+                    .trim()
+                    .to_string(),
+                value: start + colon + 1..end,
+            });
+        }
+
+        on = end + 1;
+    }
+}
+
+//The file next to a test with the given extension in place of .qrt
+fn sidecar(path: &Path, extension: &str) -> PathBuf {
+    path.with_extension(extension)
+}
+
+fn read_sidecar(path: &Path, extension: &str) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(sidecar(path, extension)) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+//Writes a sidecar file, or removes it when there's nothing to expect, since that's what a missing one means
+fn write_sidecar(path: &Path, extension: &str, contents: &[u8]) -> io::Result<()> {
+    let sidecar = sidecar(path, extension);
+
+    if !contents.is_empty() {
+        fs::write(sidecar, contents)
+    } else if sidecar.exists() {
+        fs::remove_file(sidecar)
+    } else {
+        Ok(())
+    }
+}
+
+//A line by line diff, with lines only expected marked - and lines only given marked +
+pub fn diff(expected: &str, actual: &str) -> String {
+    let newlines = (expected.ends_with('\n'), actual.ends_with('\n'));
+    let (expected, actual): (Vec<&str>, Vec<&str>) =
+        (expected.lines().collect(), actual.lines().collect());
+
+    //common[i][j] is the length of the longest common subsequence of expected[i..] and actual[j..]
+    let mut common = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j, mut diff) = (0, 0, String::new());
+
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            diff += &format!("  {}\n", expected[i]);
+            i += 1;
+            j += 1;
+        } else if i < expected.len() && (j == actual.len() || common[i + 1][j] >= common[i][j + 1])
+        {
+            diff += &format!("- {}\n", expected[i]);
+            i += 1;
+        } else {
+            diff += &format!("+ {}\n", actual[j]);
+            j += 1;
+        }
+    }
+
+    match newlines {
+        (true, false) => diff += "(only the expected text ends with a newline)\n",
+        (false, true) => diff += "(only the given text ends with a newline)\n",
+        _ => (),
+    }
+
+    diff
+}
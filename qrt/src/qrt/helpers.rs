@@ -40,52 +40,105 @@ pub fn unpack_operator(packed: &Abstract) -> Option<u8> {
 }
 
 //Helper function, used to find the end of secondary args. Expects to start the character directly after the first bracket.
-//Returns the position directly after the pairing bracket.
-pub fn find_bracket_pair(program: &[u8], mut on: usize) -> usize {
+//Returns the position directly after the pairing bracket, or None if the program ends before the bracket is paired.
+pub fn find_bracket_pair(program: &[u8], mut on: usize) -> Option<usize> {
     let (mut bracket_number, mut gestalt, mut escape) = (1, false, false);
 
     while bracket_number != 0 {
-        match program[on] {
+        match program.get(on)? {
+            //Within gestalts, brackets don't count and only an unescaped quote ends them, like when they're parsed
+            c if gestalt => match c {
+                b'"' if !escape => gestalt = false,
+                b'\\' if !escape => escape = true,
+                _ => escape = false,
+            },
+
             //Matches for opening brackets
-            b'{' => {
-                if !gestalt {
-                    bracket_number += 1;
-                }
-            }
+            b'{' => bracket_number += 1,
 
             //Matches for closing brackets
-            b'}' => {
-                if !gestalt {
-                    bracket_number -= 1;
-                }
-            }
-
-            //Matches for quotes (gestalt initiation, termination, or escaped quote)
-            b'"' => {
-                if !gestalt {
-                    gestalt = true;
-                } else if !escape {
-                    gestalt = false;
-                }
-            }
-
-            //Matches for backslashes (escape initiation or escaped backslash)
-            b'\\' => {
-                if !escape {
-                    escape = true
-                }
-            }
-
-            //Matches for other characters and unescapes
-            _ => {
-                if escape {
-                    escape = false
-                }
-            }
+            b'}' => bracket_number -= 1,
+
+            //Matches for quotes (gestalt initiation)
+            b'"' => gestalt = true,
+
+            _ => (),
         }
 
         on += 1;
     }
 
-    on
+    Some(on)
+}
+
+//Helper function, finds the first position of a subslice within a gestalt, used by find, split, and replace.
+pub fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+//Helper function, splits a gestalt by every occurence of a delimiter. Expects a non-empty delimiter.
+pub fn split_subslice(gestalt: &[u8], delimiter: &[u8]) -> Vec<Vec<u8>> {
+    let (mut pieces, mut rest) = (Vec::new(), gestalt);
+
+    while let Some(i) = find_subslice(rest, delimiter) {
+        pieces.push(rest[..i].to_vec());
+        rest = &rest[i + delimiter.len()..];
+    }
+
+    pieces.push(rest.to_vec());
+
+    pieces
+}
+
+//Helper function, turns a Linear into a position within a Set or Gestalt of the given length.
+//Non-integers are floored and negative indices count back from the end. Positions equal to the length are allowed,
+//since they are valid for slicing and insertion, so callers accessing an element must check against the length.
+pub fn resolve_bound(index: f64, len: usize) -> Option<usize> {
+    let index = index.floor();
+
+    let index = if index < 0.0 {
+        len as f64 + index
+    } else {
+        index
+    };
+
+    if index >= 0.0 && index <= len as f64 {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+//Helper function, like resolve_bound but only for positions of existing elements.
+pub fn resolve_index(index: f64, len: usize) -> Option<usize> {
+    resolve_bound(index, len).filter(|i| *i < len)
+}
+
+//Helper function, unpacks the [start, end] or [start] Set given to slicing into a checked range.
+pub fn resolve_range(range: &[Var], len: usize) -> Result<(usize, usize), &'static str> {
+    let (start, end) = match range {
+        [Var::Linear(start)] => (*start, len as f64),
+        [Var::Linear(start), Var::Linear(end)] => (*start, *end),
+        _ => return Err("Slice range must be a Set of one or two Linears"),
+    };
+
+    match (resolve_bound(start, len), resolve_bound(end, len)) {
+        (Some(start), Some(end)) if start <= end => Ok((start, end)),
+        (Some(_), Some(_)) => Err("Slice start is after its end"),
+        _ => Err("Slice range out of bounds"),
+    }
+}
+
+//Helper function, unpacks the [index, value] Set given to set-at-index and insertion.
+pub fn unpack_index_pair(pair: &[Var]) -> Result<(f64, Var), &'static str> {
+    match pair {
+        [Var::Linear(index), value] => Ok((*index, value.clone())),
+        _ => Err("Expected a Set of an index and a value"),
+    }
 }
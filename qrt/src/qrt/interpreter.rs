@@ -0,0 +1,167 @@
+use super::{
+    evaluate::evaluate_with,
+    io::{Io, StdIo},
+    structs::*,
+    threads::Threads,
+};
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+//A function written in Rust, registered on an interpreter so QRT code can call it by name with h.
+//Evaluations spawned onto other threads can call it too, so it has to be Send and Sync.
+pub type HostFunction = Arc<dyn Fn(&[Var]) -> Result<Var, QrtError> + Send + Sync>;
+
+//Holds everything an evaluation needs from outside of the QRT code itself.
+//Jumps and macros evaluate within the same interpreter as the code that called them.
+//The console is shared with every interpreter spawned from this one, so it's kept behind a lock.
+pub struct Interpreter<I: Io = StdIo, H: Hook = ()> {
+    io: Arc<Mutex<I>>,
+    pub hook: H,
+    functions: HashMap<String, HostFunction>,
+    cancel: CancelToken,
+    threads: Threads,
+}
+
+impl Interpreter<StdIo> {
+    //An interpreter attached to the real console
+    pub fn new() -> Interpreter<StdIo> {
+        Interpreter::with_io(StdIo)
+    }
+}
+
+impl Default for Interpreter<StdIo> {
+    fn default() -> Interpreter<StdIo> {
+        Interpreter::new()
+    }
+}
+
+impl<I: Io> Interpreter<I> {
+    //An interpreter attached to any console, such as a BufferIo for capturing output
+    pub fn with_io(io: I) -> Interpreter<I> {
+        Interpreter {
+            io: Arc::new(Mutex::new(io)),
+            hook: (),
+            functions: HashMap::new(),
+            cancel: CancelToken::default(),
+            threads: Threads::default(),
+        }
+    }
+}
+
+impl<I: Io, H: Hook> Interpreter<I, H> {
+    //The same interpreter, with a hook that gets called before every step of evaluation
+    pub fn with_hook<J: Hook>(self, hook: J) -> Interpreter<I, J> {
+        Interpreter {
+            io: self.io,
+            hook,
+            functions: self.functions,
+            cancel: self.cancel,
+            threads: self.threads,
+        }
+    }
+
+    //Registers a function that QRT code can call as h"name"{[arguments]}, replacing any with the same name.
+    //An error it gives is thrown from where it was called, as though the code itself had thrown it.
+    pub fn register<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(&[Var]) -> Result<Var, QrtError> + Send + Sync + 'static,
+    {
+        self.functions.insert(name.to_string(), Arc::new(function));
+    }
+
+    //A token that stops whatever the interpreter is evaluating when cancelled, which can be sent to other threads
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    //The threads and channels evaluations have made
+    pub fn threads(&self) -> &Threads {
+        &self.threads
+    }
+
+    //The console, which stays locked (so spawned evaluations can't use it) until what this gives is dropped
+    pub fn io(&self) -> MutexGuard<'_, I> {
+        self.io
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    //An interpreter for evaluations spawned onto other threads, without the hook (which only watches this thread).
+    //It shares everything else, even the console, so cancelling this interpreter cancels it as well.
+    pub fn spawned(&self) -> Interpreter<I> {
+        Interpreter {
+            io: self.io.clone(),
+            hook: (),
+            functions: self.functions.clone(),
+            cancel: self.cancel.clone(),
+            threads: self.threads.clone(),
+        }
+    }
+
+    //The host function registered with the given name
+    pub fn function(&self, name: &str) -> Option<&HostFunction> {
+        self.functions.get(name)
+    }
+
+    pub fn evaluate(&mut self, program: &[u8], input: &Var) -> Evaluation {
+        evaluate_with(self, program, input)
+    }
+}
+
+//Stops evaluation from anywhere, even another thread. It's checked before every step, throwing an error once cancelled.
+//An interpreter stays cancelled (so everything it evaluates throws that error) until its token is reset.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+//Something that watches evaluation as it happens, like a debugger or profiler.
+pub trait Hook {
+    //Called before every step, giving an error stops evaluation as though the code itself had thrown it
+    fn step(&mut self, _step: &Step) -> Result<(), String> {
+        Ok(())
+    }
+
+    //Called whenever an operator gives a value, with its primary and secondary arguments.
+    //Evaluations (!) give the jump's position or the evaluated Gestalt as their primary argument.
+    fn applied(&mut self, _step: &Step, _operator: u8, _a: &Var, _b: &Var, _result: &Var) {}
+}
+
+//No hook at all, which is what interpreters have unless they're given one
+impl Hook for () {}
+
+//Where evaluation is, and everything it has, right before it takes a step.
+//on is the position of the step within program, which is only part of the code the interpreter was given when
+//evaluating a jump (origin being where that part starts), or a Gestalt evaluated as code (which has no origin).
+//depth is how many evaluations the step is nested within, 0 being the code the interpreter was given.
+pub struct Step<'a> {
+    pub program: &'a [u8],
+    pub on: usize,
+    pub origin: Option<usize>,
+    pub depth: usize,
+    pub input: &'a Var,
+    pub stack: &'a VecDeque<Abstract>,
+    pub map: &'a HashMap<String, Var>,
+}
@@ -0,0 +1,110 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, Read, Write},
+};
+
+//Everything QRT code prints or reads goes through this, so embedding hosts and tests can swap the console out.
+//Evaluations spawned onto other threads share it, so it has to be Send.
+pub trait Io: Send + 'static {
+    //Writes bytes to standard output
+    fn write_out(&mut self, bytes: &[u8]) -> io::Result<()>;
+
+    //Writes bytes to standard error
+    fn write_err(&mut self, bytes: &[u8]) -> io::Result<()>;
+
+    //Reads one line without its line ending, or None if the input has ended
+    fn read_line(&mut self) -> io::Result<Option<Vec<u8>>>;
+
+    //Reads everything left in the input
+    fn read_all(&mut self) -> io::Result<Vec<u8>>;
+}
+
+//The real console, used when QRT is run from the terminal
+pub struct StdIo;
+
+impl Io for StdIo {
+    fn write_out(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(bytes)?;
+        stdout.flush()
+    }
+
+    fn write_err(&mut self, bytes: &[u8]) -> io::Result<()> {
+        io::stderr().lock().write_all(bytes)
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut line: Vec<u8> = Vec::new();
+
+        if io::stdin().lock().read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(strip_line_ending(line)))
+    }
+
+    fn read_all(&mut self) -> io::Result<Vec<u8>> {
+        let mut all: Vec<u8> = Vec::new();
+        io::stdin().lock().read_to_end(&mut all)?;
+        Ok(all)
+    }
+}
+
+//An in-memory console, reading from a given input and keeping everything written so it can be looked at afterwards
+#[derive(Default)]
+pub struct BufferIo {
+    pub input: VecDeque<u8>,
+    pub output: Vec<u8>,
+    pub errors: Vec<u8>,
+}
+
+impl BufferIo {
+    pub fn new(input: &[u8]) -> BufferIo {
+        BufferIo {
+            input: input.iter().copied().collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Io for BufferIo {
+    fn write_out(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.output.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn write_err(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.errors.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.input.is_empty() {
+            return Ok(None);
+        }
+
+        let end = match self.input.iter().position(|c| *c == b'\n') {
+            Some(i) => i + 1,
+            None => self.input.len(),
+        };
+
+        Ok(Some(strip_line_ending(self.input.drain(..end).collect())))
+    }
+
+    fn read_all(&mut self) -> io::Result<Vec<u8>> {
+        Ok(self.input.drain(..).collect())
+    }
+}
+
+//Removes a trailing \n or \r\n from a line
+fn strip_line_ending(mut line: Vec<u8>) -> Vec<u8> {
+    if line.last() == Some(&b'\n') {
+        line.pop();
+
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    }
+
+    line
+}
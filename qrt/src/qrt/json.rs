@@ -0,0 +1,184 @@
+use super::structs::Var;
+use serde::{
+    de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor},
+    ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer},
+};
+use std::fmt;
+
+//QRT has no map type, so JSON objects are read in as a Set of [key, value] pairs (in the order they were written).
+//Vars serialize every Set as an array, wrap them in Objects to write those pairs back out as objects instead.
+pub struct Objects<'a>(pub &'a Var);
+
+//The shared serializer behind both Var and Objects, carrying whether pair Sets should become objects
+struct Json<'a> {
+    var: &'a Var,
+    objects: bool,
+}
+impl Json<'_> {
+    //A Set counts as an object when it is made of nothing but [Gestalt, value] pairs with text keys
+    fn is_object(set: &[Var]) -> bool {
+        !set.is_empty()
+            && set.iter().all(|pair| match pair {
+                Var::Set(pair) => {
+                    matches!(pair.as_slice(), [Var::Gestalt(key), _] if core::str::from_utf8(key).is_ok())
+                }
+                _ => false,
+            })
+    }
+}
+impl Serialize for Json<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.var {
+            Var::Void(_) => serializer.serialize_unit(),
+
+            //Whole Linears are written without a fraction, as long as they can be held exactly
+            Var::Linear(l) if l.fract() == 0.0 && l.abs() < 9007199254740992.0 => {
+                serializer.serialize_i64(*l as i64)
+            }
+            Var::Linear(l) => serializer.serialize_f64(*l),
+
+            //Gestalts that aren't text are still kept intact, for formats that can hold raw bytes
+            Var::Gestalt(g) => match core::str::from_utf8(g) {
+                Ok(s) => serializer.serialize_str(s),
+                Err(_) => serializer.serialize_bytes(g),
+            },
+
+            Var::Set(set) if self.objects && Json::is_object(set) => {
+                let mut map = serializer.serialize_map(Some(set.len()))?;
+
+                for pair in set {
+                    if let Var::Set(pair) = pair {
+                        map.serialize_entry(
+                            &Json {
+                                var: &pair[0],
+                                objects: true,
+                            },
+                            &Json {
+                                var: &pair[1],
+                                objects: true,
+                            },
+                        )?;
+                    }
+                }
+
+                map.end()
+            }
+
+            Var::Set(set) => {
+                let mut seq = serializer.serialize_seq(Some(set.len()))?;
+
+                for var in set {
+                    seq.serialize_element(&Json {
+                        var,
+                        objects: self.objects,
+                    })?;
+                }
+
+                seq.end()
+            }
+
+            //KillIDs only mean something to the loop that is running, so they can't leave the program
+            Var::Kill(_) => Err(ser::Error::custom("KillIDs cannot be serialized")),
+        }
+    }
+}
+
+impl Serialize for Var {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Json {
+            var: self,
+            objects: false,
+        }
+        .serialize(serializer)
+    }
+}
+impl Serialize for Objects<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Json {
+            var: self.0,
+            objects: true,
+        }
+        .serialize(serializer)
+    }
+}
+
+struct VarVisitor;
+impl<'de> Visitor<'de> for VarVisitor {
+    type Value = Var;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a null, boolean, number, string, array or object")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Var, E> {
+        Ok(Var::void())
+    }
+    fn visit_none<E: de::Error>(self) -> Result<Var, E> {
+        Ok(Var::void())
+    }
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Var, D::Error> {
+        Var::deserialize(deserializer)
+    }
+
+    //Booleans become Linears the same way the logical operators give them
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Var, E> {
+        Ok(Var::Linear(if v { 1.0 } else { 0.0 }))
+    }
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Var, E> {
+        Ok(Var::Linear(v as f64))
+    }
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Var, E> {
+        Ok(Var::Linear(v as f64))
+    }
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Var, E> {
+        Ok(Var::Linear(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Var, E> {
+        Ok(Var::Gestalt(v.into()))
+    }
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Var, E> {
+        Ok(Var::Gestalt(v.to_vec()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Var, A::Error> {
+        let mut set = Vec::new();
+
+        while let Some(var) = seq.next_element()? {
+            set.push(var);
+        }
+
+        Ok(Var::Set(set))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Var, A::Error> {
+        let mut set = Vec::new();
+
+        while let Some((key, value)) = map.next_entry::<Var, Var>()? {
+            set.push(Var::Set(vec![key, value]));
+        }
+
+        Ok(Var::Set(set))
+    }
+}
+
+impl<'de> Deserialize<'de> for Var {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Var, D::Error> {
+        deserializer.deserialize_any(VarVisitor)
+    }
+}
+
+//Reads a JSON document held in a Gestalt
+pub fn parse(json: &[u8]) -> Result<Var, String> {
+    serde_json::from_slice(json).map_err(|e| format!("Invalid JSON: {}", e))
+}
+
+//Writes a var out as JSON, with pair Sets as objects if asked to
+pub fn stringify(var: &Var, objects: bool) -> Result<Vec<u8>, String> {
+    if objects {
+        serde_json::to_vec(&Objects(var))
+    } else {
+        serde_json::to_vec(var)
+    }
+    .map_err(|e| format!("Could not write JSON: {}", e))
+}
@@ -0,0 +1,248 @@
+use super::{operate::operate, structs::Var, syntax::*};
+use std::{collections::HashSet, ops::Range};
+
+//Finds mistakes that would otherwise only show up when a program runs (or never show up at all).
+//Each warning has a code, so they can be looked up in the README:
+//  W001 undefined alias, W002 loop body leaves a value, W003 unused kill alias, W004 jump without its bang,
+//  W005 unreachable code, W006 operator given types it can't take
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    pub code: &'static str,
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+//Operators evaluate handles itself rather than through operate, which can't be checked by trying them
+const CONTROL: &[u8] = b"#~:!@pelrhsjctwm?";
+
+//The names a scope defines. Jump definitions get a fresh scope, just like the sub-evaluations that run them,
+//apart from test jumps defined in the program's own scope, which can use everything it defines (see golden.rs).
+#[derive(Default)]
+struct Scope {
+    names: HashSet<Vec<u8>>,
+    kills: HashSet<Vec<u8>>,
+    top: bool,
+}
+
+pub fn lint(program: &[u8]) -> Result<Vec<Warning>, SyntaxError> {
+    let mut warnings = Vec::new();
+    lint_scope(&parse(program)?, None, true, &mut warnings);
+    warnings.sort_by_key(|warning| warning.span.start);
+
+    Ok(warnings)
+}
+
+//outer is the scope whose names are defined within this one too, which only test jumps have
+fn lint_scope(block: &Block, outer: Option<&Scope>, top: bool, warnings: &mut Vec<Warning>) {
+    let mut scope = Scope {
+        names: outer.map(|outer| outer.names.clone()).unwrap_or_default(),
+        top,
+        ..Scope::default()
+    };
+
+    for (name, node) in block.definitions() {
+        if let Expression::Named(b'~', ..) = node.expression {
+            scope.kills.insert(name.clone());
+        }
+        scope.names.insert(name);
+    }
+
+    check_block(block, &scope, warnings);
+}
+
+fn check_block(block: &Block, scope: &Scope, warnings: &mut Vec<Warning>) {
+    //Anything after a terminator in the same block never runs, it is only reported once
+    if let Some(terminator) = block.items.iter().position(|item| item.terminated) {
+        if let Some(node) = block.items[terminator + 1..]
+            .iter()
+            .find_map(|item| item.node.as_ref())
+        {
+            warnings.push(Warning {
+                code: "W005",
+                message: "Unreachable code after a terminator (;)".to_string(),
+                span: node.span.clone(),
+            });
+        }
+    }
+
+    for item in &block.items {
+        if let Some(node) = &item.node {
+            check_node(node, scope, warnings);
+        }
+    }
+}
+
+fn check_node(node: &Node, scope: &Scope, warnings: &mut Vec<Warning>) {
+    match &node.expression {
+        Expression::Reference(name) if !scope.names.contains(name) => {
+            let bang = [name.as_slice(), b"!"].concat();
+
+            warnings.push(if scope.names.contains(&bang) {
+                Warning {
+                    code: "W004",
+                    message: format!(
+                        "Jump referenced without its trailing bang, did you mean ({})?",
+                        String::from_utf8_lossy(&bang)
+                    ),
+                    span: node.span.clone(),
+                }
+            } else {
+                Warning {
+                    code: "W001",
+                    message: format!("Alias {} is never defined", String::from_utf8_lossy(name)),
+                    span: node.span.clone(),
+                }
+            });
+        }
+
+        Expression::Set(elements, _) => {
+            for element in elements {
+                check_node(element, scope, warnings);
+            }
+        }
+
+        Expression::Named(b':', name, block) if scope.top && name.starts_with(b"test_") => {
+            lint_scope(block, Some(scope), false, warnings)
+        }
+        Expression::Named(b':', _, block) => lint_scope(block, None, false, warnings),
+
+        Expression::Named(b'~', name, block) => {
+            let mut killed = false;
+            block.walk_scope(&mut |node| {
+                killed |= node.expression == Expression::Reference(name.clone())
+            });
+
+            if !killed && !name.ends_with(b"_") {
+                warnings.push(Warning {
+                    code: "W003",
+                    message: format!(
+                        "Kill alias {} is never referenced, so the loop never ends",
+                        String::from_utf8_lossy(name)
+                    ),
+                    span: node.head(),
+                });
+            }
+
+            check_loop_values(block, scope, warnings);
+            check_block(block, scope, warnings);
+        }
+
+        Expression::Named(_, _, block) => check_block(block, scope, warnings),
+
+        Expression::Operation(operator, primary, block) => {
+            check_types(*operator, primary, block, node, warnings);
+            check_node(primary, scope, warnings);
+            check_block(block, scope, warnings);
+        }
+
+        _ => (),
+    }
+}
+
+//Loops only work when nothing is left on the stack at the end of their body, so anything giving a value breaks them.
+//Conditionals, printing, sending along a channel, assignment, loops and kills are the only things that give nothing.
+fn check_loop_values(block: &Block, scope: &Scope, warnings: &mut Vec<Warning>) {
+    for node in block.items.iter().filter_map(|item| item.node.as_ref()) {
+        match &node.expression {
+            Expression::Operation(b'?', _, block) => check_loop_values(block, scope, warnings),
+            Expression::Named(..) | Expression::Operation(b'p' | b'e' | b't', ..) => (),
+            Expression::Reference(name) if scope.kills.contains(name) => (),
+
+            _ => warnings.push(Warning {
+                code: "W002",
+                message: "Loop body leaves a value, which stops the loop from working".to_string(),
+                span: node.span.clone(),
+            }),
+        }
+    }
+}
+
+//A stand in value for whatever type an expression is known to give, if it can be known without running anything
+fn infer(node: &Node) -> Option<Var> {
+    match &node.expression {
+        Expression::Linear(_) | Expression::Random => Some(Var::Linear(1.0)),
+        Expression::Gestalt(_) => Some(Var::Gestalt(b"a".to_vec())),
+        Expression::Set(..) => Some(Var::Set(Vec::new())),
+        Expression::Void => Some(Var::void()),
+
+        Expression::Operation(operator, primary, block) if !CONTROL.contains(operator) => {
+            let result = operate(*operator, &infer(primary)?, &infer(secondary(block)?)?).ok()?;
+            infer_type(&result)
+        }
+
+        _ => None,
+    }
+}
+
+//The stand in for a value's type
+fn infer_type(var: &Var) -> Option<Var> {
+    match var {
+        Var::Linear(_) => Some(Var::Linear(1.0)),
+        Var::Gestalt(_) => Some(Var::Gestalt(b"a".to_vec())),
+        Var::Set(_) => Some(Var::Set(Vec::new())),
+        Var::Void(_) => Some(Var::void()),
+        Var::Kill(_) => None,
+    }
+}
+
+//The value a secondary argument gives, when it's a single expression
+fn secondary(block: &Block) -> Option<&Node> {
+    match block.items.as_slice() {
+        [item] if !item.terminated => item.node.as_ref(),
+        _ => None,
+    }
+}
+
+fn check_types(
+    operator: u8,
+    primary: &Node,
+    block: &Block,
+    node: &Node,
+    warnings: &mut Vec<Warning>,
+) {
+    let span = node.span.start..node.span.start + 1;
+
+    if operator == b'?' {
+        if let Some(condition) = infer(primary).filter(|var| !matches!(var, Var::Linear(_))) {
+            warnings.push(Warning {
+                code: "W006",
+                message: format!(
+                    "Conditional given a {} instead of a Linear",
+                    condition.type_name()
+                ),
+                span,
+            });
+        }
+        return;
+    }
+
+    if CONTROL.contains(&operator) {
+        return;
+    }
+
+    if operate(operator, &Var::void(), &Var::void()) == Err("Invalid operator".to_string()) {
+        warnings.push(Warning {
+            code: "W006",
+            message: format!("Unknown operator {}", operator as char),
+            span,
+        });
+        return;
+    }
+
+    if let (Some(a), Some(b)) = (infer(primary), secondary(block).and_then(infer)) {
+        if let Err(error) = operate(operator, &a, &b) {
+            if error == "Invalid operand types" || error == "Invalid types for operator" {
+                warnings.push(Warning {
+                    code: "W006",
+                    message: format!(
+                        "Operator {} can't take a {} and a {}",
+                        operator as char,
+                        a.type_name(),
+                        b.type_name()
+                    ),
+                    span,
+                });
+            }
+        }
+    }
+}
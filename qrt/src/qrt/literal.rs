@@ -0,0 +1,248 @@
+use super::structs::Var;
+use std::fmt;
+
+//Writes vars out as QRT source that evaluates back to the same var, and reads that source back without running it.
+//Values with no literal of their own use the constant expression that makes them: negative Linears are -0{n},
+//infinities and NaN are /1{0} and /0{0}, and Gestalts that aren't valid text are B[bytes]{_}.
+impl Var {
+    pub fn to_qrt_literal(&self) -> String {
+        let mut literal = String::new();
+        write_literal(self, &mut literal);
+        literal
+    }
+
+    //Reads a single literal (as written by to_qrt_literal), allowing whitespace and comments around its parts
+    pub fn from_qrt_literal(source: &[u8]) -> Result<Var, &'static str> {
+        let mut reader = Reader { source, on: 0 };
+
+        let var = reader.literal()?;
+
+        reader.skip_blank()?;
+        if reader.on < source.len() {
+            return Err("Unexpected characters after literal");
+        }
+
+        Ok(var)
+    }
+}
+
+impl fmt::Display for Var {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_qrt_literal())
+    }
+}
+
+fn write_literal(var: &Var, literal: &mut String) {
+    match var {
+        Var::Void(_) => literal.push('_'),
+
+        Var::Linear(l) if l.is_nan() => literal.push_str("/0{0}"),
+        Var::Linear(l) if l.is_sign_negative() => {
+            literal.push_str("-0{");
+            write_literal(&Var::Linear(-l), literal);
+            literal.push('}');
+        }
+        Var::Linear(l) if l.is_infinite() => literal.push_str("/1{0}"),
+        //Rust never writes floats with an exponent, so this is always digits with at most one point
+        Var::Linear(l) => literal.push_str(&l.to_string()),
+
+        Var::Gestalt(g) => match core::str::from_utf8(g) {
+            Ok(s) => {
+                literal.push('"');
+                for c in s.chars() {
+                    if c == '"' || c == '\\' {
+                        literal.push('\\');
+                    }
+                    literal.push(c);
+                }
+                literal.push('"');
+            }
+
+            Err(_) => {
+                literal.push_str("B[");
+                for (i, byte) in g.iter().enumerate() {
+                    if i > 0 {
+                        literal.push_str(", ");
+                    }
+                    literal.push_str(&byte.to_string());
+                }
+                literal.push_str("]{_}");
+            }
+        },
+
+        Var::Set(set) => {
+            literal.push('[');
+            for (i, var) in set.iter().enumerate() {
+                if i > 0 {
+                    literal.push_str(", ");
+                }
+                write_literal(var, literal);
+            }
+            literal.push(']');
+        }
+
+        //KillIDs only exist while their loop runs, so the closest thing that can be written is a commented Void
+        Var::Kill(n) => literal.push_str(&format!("\\KillID({})\\_", n)),
+    }
+}
+
+struct Reader<'a> {
+    source: &'a [u8],
+    on: usize,
+}
+impl Reader<'_> {
+    //Skips whitespace and comments, the same things evaluate passes over between expressions
+    fn skip_blank(&mut self) -> Result<(), &'static str> {
+        while let Some(c) = self.source.get(self.on) {
+            match c {
+                b' ' | b'\n' | b'\r' | b'\t' => self.on += 1,
+
+                b'\\' => match self.source[self.on + 1..].iter().position(|c| *c == b'\\') {
+                    Some(end) => self.on += end + 2,
+                    None => return Err("No closing backslash found for comment"),
+                },
+
+                _ => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    //Consumes the given bytes exactly, or errors
+    fn expect(&mut self, expected: &[u8]) -> Result<(), &'static str> {
+        self.skip_blank()?;
+
+        if self.source[self.on..].starts_with(expected) {
+            self.on += expected.len();
+            Ok(())
+        } else {
+            Err("Invalid literal")
+        }
+    }
+
+    fn literal(&mut self) -> Result<Var, &'static str> {
+        self.skip_blank()?;
+
+        match self.source.get(self.on) {
+            None => Err("Expected a literal"),
+
+            Some(b'_') => {
+                self.on += 1;
+                Ok(Var::void())
+            }
+
+            Some(b'0'..=b'9') => {
+                let start = self.on;
+                while let Some(b'0'..=b'9' | b'.') = self.source.get(self.on) {
+                    self.on += 1;
+                }
+
+                match core::str::from_utf8(&self.source[start..self.on]).map(str::parse::<f64>) {
+                    Ok(Ok(l)) => Ok(Var::Linear(l)),
+                    _ => Err("Incorrect linear formatting"),
+                }
+            }
+
+            Some(b'-') => {
+                self.expect(b"-")?;
+                self.expect(b"0")?;
+                self.expect(b"{")?;
+                let negated = match self.literal()? {
+                    Var::Linear(l) => Var::Linear(-l),
+                    _ => return Err("Only Linears can be negated"),
+                };
+                self.expect(b"}")?;
+
+                Ok(negated)
+            }
+
+            Some(b'/') => {
+                self.expect(b"/")?;
+                let dividend = self.literal()?;
+                self.expect(b"{")?;
+                self.expect(b"0")?;
+                self.expect(b"}")?;
+
+                match dividend {
+                    Var::Linear(0.0) => Ok(Var::Linear(f64::NAN)),
+                    Var::Linear(1.0) => Ok(Var::Linear(f64::INFINITY)),
+                    _ => Err("Only /1{0} and /0{0} are literals"),
+                }
+            }
+
+            Some(b'"') => {
+                let mut gestalt = Vec::new();
+                let mut escape = false;
+
+                loop {
+                    self.on += 1;
+                    match self.source.get(self.on) {
+                        None => return Err("No closing quote found for Gestalt literal"),
+                        Some(b'"') if !escape => break,
+                        Some(b'\\') if !escape => escape = true,
+                        Some(c) => {
+                            escape = false;
+                            gestalt.push(*c);
+                        }
+                    }
+                }
+                self.on += 1;
+
+                Ok(Var::Gestalt(gestalt))
+            }
+
+            Some(b'[') => {
+                self.on += 1;
+                Ok(Var::Set(self.elements()?))
+            }
+
+            Some(b'B') => {
+                self.expect(b"B")?;
+                self.expect(b"[")?;
+                let bytes = self
+                    .elements()?
+                    .into_iter()
+                    .map(|var| match var {
+                        Var::Linear(l) if l.fract() == 0.0 && (0.0..=255.0).contains(&l) => {
+                            Ok(l as u8)
+                        }
+                        _ => Err("Bytes must be whole Linears from 0 to 255"),
+                    })
+                    .collect::<Result<Vec<u8>, _>>()?;
+                self.expect(b"{")?;
+                self.expect(b"_")?;
+                self.expect(b"}")?;
+
+                Ok(Var::Gestalt(bytes))
+            }
+
+            Some(_) => Err("Invalid literal"),
+        }
+    }
+
+    //Reads comma separated literals up to a closing bracket, the opening bracket having already been consumed
+    fn elements(&mut self) -> Result<Vec<Var>, &'static str> {
+        let mut set = Vec::new();
+
+        self.skip_blank()?;
+        if self.source.get(self.on) == Some(&b']') {
+            self.on += 1;
+            return Ok(set);
+        }
+
+        loop {
+            set.push(self.literal()?);
+            self.skip_blank()?;
+
+            match self.source.get(self.on) {
+                Some(b',') => self.on += 1,
+                Some(b']') => {
+                    self.on += 1;
+                    return Ok(set);
+                }
+                _ => return Err("No closing bracket found"),
+            }
+        }
+    }
+}
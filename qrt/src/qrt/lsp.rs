@@ -0,0 +1,422 @@
+use super::{
+    lint::lint,
+    syntax::{parse, Block, Expression, Node},
+};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    ops::Range,
+    sync::OnceLock,
+};
+
+//A language server for QRT, spoken over JSON-RPC (as the language server protocol describes) on the given streams.
+//Documents are always sent whole, and every change to one publishes its syntax errors and lint warnings.
+//It also answers go to definition for references, hover for operators and references, and completion of aliases.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut documents: HashMap<String, String> = HashMap::new();
+
+    while let Some(message) = read_message(&mut input)? {
+        let id = message.get("id").cloned();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+
+        let result = match message["method"].as_str() {
+            Some("initialize") => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": ["("] }
+                },
+                "serverInfo": { "name": "qrt-lsp" }
+            }),
+
+            Some("shutdown") => Value::Null,
+            Some("exit") => return Ok(()),
+
+            Some("textDocument/didOpen") => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                documents.insert(uri.clone(), text.to_string());
+                publish_diagnostics(&mut output, &uri, text)?;
+                continue;
+            }
+            Some("textDocument/didChange") => {
+                if let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    documents.insert(uri.clone(), text.to_string());
+                    publish_diagnostics(&mut output, &uri, text)?;
+                }
+                continue;
+            }
+            Some("textDocument/didClose") => {
+                documents.remove(&uri);
+                write_message(
+                    &mut output,
+                    &json!({
+                        "jsonrpc": "2.0",
+                        "method": "textDocument/publishDiagnostics",
+                        "params": { "uri": uri, "diagnostics": [] }
+                    }),
+                )?;
+                continue;
+            }
+
+            Some(
+                method @ ("textDocument/definition"
+                | "textDocument/hover"
+                | "textDocument/completion"),
+            ) => match documents.get(&uri) {
+                Some(text) => {
+                    let on = offset(text, &params["position"]);
+                    match method {
+                        "textDocument/definition" => definition(text, on)
+                            .map(|span| json!({ "uri": uri, "range": range(text, span) }))
+                            .unwrap_or(Value::Null),
+                        "textDocument/hover" => hover(text, on).unwrap_or(Value::Null),
+                        _ => completion(text, on),
+                    }
+                }
+                None => Value::Null,
+            },
+
+            //Requests that aren't supported get an error, anything else (notifications, responses) is ignored
+            Some(_) if id.is_some() => {
+                write_message(
+                    &mut output,
+                    &json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32601, "message": "Method not found" }
+                    }),
+                )?;
+                continue;
+            }
+            _ => continue,
+        };
+
+        write_message(
+            &mut output,
+            &json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        )?;
+    }
+
+    Ok(())
+}
+
+//Reads one message, giving None once the input has ended. Messages that aren't valid JSON are skipped.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    loop {
+        let mut length = None;
+
+        loop {
+            let mut header = String::new();
+            if input.read_line(&mut header)? == 0 {
+                return Ok(None);
+            }
+
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+
+        let mut content = vec![0; length.unwrap_or(0)];
+        input.read_exact(&mut content)?;
+
+        if let Ok(message) = serde_json::from_slice(&content) {
+            return Ok(Some(message));
+        }
+    }
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
+
+//Positions are given as lines and UTF-16 characters, converted here to and from byte offsets into the text
+fn position(text: &str, mut on: usize) -> Value {
+    on = on.min(text.len());
+    while !text.is_char_boundary(on) {
+        on -= 1;
+    }
+
+    let line_start = text[..on].rfind('\n').map_or(0, |newline| newline + 1);
+
+    json!({
+        "line": text[..on].matches('\n').count(),
+        "character": text[line_start..on].encode_utf16().count()
+    })
+}
+
+fn offset(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+
+    let line_start = match line {
+        0 => 0,
+        _ => match text.match_indices('\n').nth(line - 1) {
+            Some((newline, _)) => newline + 1,
+            None => return text.len(),
+        },
+    };
+
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+
+    text.len()
+}
+
+fn range(text: &str, span: Range<usize>) -> Value {
+    json!({ "start": position(text, span.start), "end": position(text, span.end) })
+}
+
+fn publish_diagnostics(output: &mut impl Write, uri: &str, text: &str) -> io::Result<()> {
+    let diagnostics: Vec<Value> = match lint(text.as_bytes()) {
+        Ok(warnings) => warnings
+            .into_iter()
+            .map(|warning| {
+                json!({
+                    "range": range(text, warning.span),
+                    "severity": 2,
+                    "code": warning.code,
+                    "source": "qrt",
+                    "message": warning.message
+                })
+            })
+            .collect(),
+
+        Err((msg, on)) => vec![json!({
+            "range": range(text, on..on + 1),
+            "severity": 1,
+            "source": "qrt",
+            "message": msg
+        })],
+    };
+
+    write_message(
+        output,
+        &json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics }
+        }),
+    )
+}
+
+//The innermost node the given offset is in
+fn node_at(program: &Block, on: usize) -> Option<&Node> {
+    let mut found: Option<&Node> = None;
+
+    program.walk(&mut |node| {
+        if node.span.contains(&on) && found.is_none_or(|found| node.span.len() <= found.span.len())
+        {
+            found = Some(node);
+        }
+    });
+
+    found
+}
+
+//The block whose aliases the code at the given offset can see, which is the innermost jump definition around it
+fn scope_at(program: &Block, on: usize) -> &Block {
+    let mut scope = program;
+
+    program.walk(&mut |node| {
+        if let Expression::Named(b':', _, block) = &node.expression {
+            if block.span.contains(&on) && block.span.len() <= scope.span.len() {
+                scope = block;
+            }
+        }
+    });
+
+    scope
+}
+
+//Where the alias referenced at the given offset is defined
+fn definition(text: &str, on: usize) -> Option<Range<usize>> {
+    let program = parse(text.as_bytes()).ok()?;
+
+    match &node_at(&program, on)?.expression {
+        Expression::Reference(name) => scope_at(&program, on)
+            .definitions()
+            .into_iter()
+            .find(|(defined, _)| defined == name)
+            .map(|(_, node)| node.head()),
+        _ => None,
+    }
+}
+
+fn hover(text: &str, on: usize) -> Option<Value> {
+    let program = parse(text.as_bytes()).ok()?;
+    let node = node_at(&program, on)?;
+
+    let contents = match &node.expression {
+        Expression::Operation(operator, ..) | Expression::Named(operator, ..)
+            if on == node.span.start =>
+        {
+            let (_, overloads) = operators().iter().find(|(o, _)| o == operator)?;
+            format!(
+                "`{}` {}\n\n{}",
+                *operator as char,
+                summary(overloads),
+                overloads
+                    .iter()
+                    .map(|overload| format!("- {}", overload))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            )
+        }
+
+        Expression::Reference(name) => {
+            let (_, definition) = scope_at(&program, on)
+                .definitions()
+                .into_iter()
+                .find(|(defined, _)| defined == name)?;
+
+            let kind = match definition.expression {
+                Expression::Named(b'~', ..) => "kill alias of the loop",
+                Expression::Named(b':', ..) => "jump",
+                _ => "alias",
+            };
+
+            format!(
+                "{} defined on line {}:\n```\n{}\n```",
+                kind,
+                position(text, definition.span.start)["line"].as_u64()? + 1,
+                text[definition.span.clone()].lines().next()?
+            )
+        }
+
+        Expression::Linear(_) => "Linear literal".to_string(),
+        Expression::Gestalt(_) => "Gestalt literal".to_string(),
+        Expression::Set(..) => "Set literal".to_string(),
+        Expression::Void => "Void literal".to_string(),
+        Expression::Input => {
+            "Input, the secondary argument of the jump or macro running this code".to_string()
+        }
+        Expression::Random => "A random Linear between 0 and 1".to_string(),
+
+        _ => return None,
+    };
+
+    Some(json!({
+        "contents": { "kind": "markdown", "value": contents },
+        "range": range(text, node.span.clone())
+    }))
+}
+
+//Offers the aliases in scope, and the operators too unless a reference is being written
+fn completion(text: &str, on: usize) -> Value {
+    let in_reference = text[..on.min(text.len())].ends_with('(');
+    let mut items = Vec::new();
+
+    //A document being written often doesn't parse, so aliases are taken from the last version that did
+    if let Ok(program) =
+        parse(text.as_bytes()).or_else(|_| parse(&text.as_bytes()[..on.min(text.len())]))
+    {
+        let mut seen = Vec::new();
+
+        for (name, node) in scope_at(&program, on).definitions() {
+            if seen.contains(&name) {
+                continue;
+            }
+
+            let name_text = String::from_utf8_lossy(&name).to_string();
+            items.push(json!({
+                "label": name_text,
+                "kind": if let Expression::Named(b':', ..) = node.expression { 3 } else { 6 },
+                "insertText": if in_reference { name_text.clone() } else { format!("({})", name_text) }
+            }));
+            seen.push(name);
+        }
+    }
+
+    if !in_reference {
+        for (operator, overloads) in operators() {
+            items.push(json!({
+                "label": (*operator as char).to_string(),
+                "kind": 24,
+                "detail": summary(overloads)
+            }));
+        }
+    }
+
+    Value::Array(items)
+}
+
+//The operators, with a line for every combination of types each takes, copied from the README (the handbook), which
+//the tests check stays the same. Each operator is on a line of its own indented by 4 spaces, followed by its lines
+//indented by 8, which start with the types it takes (such as Linear-Linear) and can carry on over more.
+pub(crate) const OPERATOR_TABLE: &str = include_str!("operators.txt");
+
+const TYPES: &[&str] = &[
+    "Any", "Void", "Linear", "Gestalt", "Set", "Kill", "Name", "Code",
+];
+
+pub(crate) fn operators() -> &'static [(u8, Vec<String>)] {
+    static OPERATORS: OnceLock<Vec<(u8, Vec<String>)>> = OnceLock::new();
+
+    OPERATORS.get_or_init(|| {
+        let mut operators: Vec<(u8, Vec<String>)> = Vec::new();
+        let mut lines = OPERATOR_TABLE.lines().peekable();
+
+        while let Some(line) = lines.next() {
+            let operator = match line.strip_prefix("    ").map(str::as_bytes) {
+                Some([operator]) if !operator.is_ascii_whitespace() => *operator,
+                _ => continue,
+            };
+
+            let mut overloads: Vec<String> = Vec::new();
+            while let Some(row) = lines.peek().and_then(|line| line.strip_prefix("        ")) {
+                let types = row.split([' ', '(']).next().unwrap_or_default();
+                match overloads.last_mut() {
+                    Some(last)
+                        if !types.contains('-')
+                            || !types.split('-').all(|t| TYPES.contains(&t)) =>
+                    {
+                        last.push(' ');
+                        last.push_str(row.trim());
+                    }
+                    _ => overloads.push(row.trim().to_string()),
+                }
+                lines.next();
+            }
+
+            operators.push((operator, overloads));
+        }
+
+        operators
+    })
+}
+
+//The names of every combination of types the operator takes, like "addition, linear coercion, ..."
+fn summary(overloads: &[String]) -> String {
+    overloads
+        .iter()
+        .filter_map(|overload| Some(overload.split_once('(')?.1.split_once(')')?.0))
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
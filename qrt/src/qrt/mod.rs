@@ -1,3 +1,23 @@
+pub mod debug;
 pub mod evaluate;
+pub mod ffi;
+pub mod files;
+pub mod format;
+pub mod golden;
 pub mod helpers;
+pub mod interpreter;
+pub mod io;
+pub mod json;
+pub mod lint;
+pub mod literal;
+pub mod lsp;
+pub mod operate;
+pub mod profile;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod session;
+pub mod snapshot;
 pub mod structs;
+pub mod syntax;
+pub mod threads;
+pub mod trace;
@@ -0,0 +1,502 @@
+use super::{helpers::*, json, structs::*};
+
+//Applies any operator that only needs the values of its two arguments, leaving the ones that touch the
+//program, the map or the disk (#, !, @) to evaluate. Kept out of evaluate itself so the stack frame that
+//every jump and macro recurses into stays small.
+pub fn operate(operator: u8, a: &Var, b: &Var) -> Result<Var, String> {
+    //This is for use inside operation closures where the return type is a simpler Result with only a msg.
+    macro_rules! cstring_from_utf8 {
+        ($utf8:expr) => {{
+            if let Ok(s) = String::from_utf8($utf8) {
+                s
+            } else {
+                return Err("Invalid Gestalt chars".into());
+            }
+        }};
+    }
+
+    //This macro generates mutliple type match statements for multiple operation variations (Linear-Linear, Gestalt-Linear, etc.)
+    macro_rules! multi_operate {
+        ( $( ($vartypea:tt, $vartypeb:tt, $outtype:tt $operation:expr) ),*) => {{
+            match (a, b) {
+
+                $(
+                    (Var::$vartypea(a), Var::$vartypeb(b)) => {
+                        $operation(
+                            a.clone(),
+                            b.clone()
+                        )
+                        .map(Var::$outtype)
+                        .map_err(String::from)
+                    }
+                )*
+
+                (Var::Void(_), _) | (_, Var::Void(_)) => Ok(Var::void()),
+
+                _ => Err("Invalid operand types".to_string())
+            }
+        }};
+    }
+
+    match operator {
+        //ARTITHMETIC
+
+        //Addition
+        b'+' => {
+            multi_operate!(
+
+                (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {Ok(a + b)}),
+
+                (Linear, Gestalt, Linear|a: f64, b: Vec<u8>| -> Result<f64, &str> {
+                    if let Ok(b) = cstring_from_utf8!(b).parse::<f64>() {
+                        Ok(a + b)
+                    } else {
+                        Err("Could not coerce Gestalt to Linear")
+                    }
+                }),
+
+                (Gestalt, Linear, Gestalt|a: Vec<u8>, b: f64| -> Result<Vec<u8>, &str> {
+                    Ok((cstring_from_utf8!(a) + &format!("{}", b)).into())
+                }),
+
+                (Gestalt, Gestalt, Gestalt|a: Vec<u8>, b: Vec<u8>| -> Result<Vec<u8>, &str> {
+                    Ok((cstring_from_utf8!(a) + &cstring_from_utf8!(b)).into())
+                }),
+
+                //Please note that all the disgusting, unperformant set cloning in this language was intended to be remedied with a kind of
+                //reference system, but that rust fundamentally does not allow multiple mutable references so that couldn't be done.
+                (Set, Linear, Set|a: Vec<Var>, b: f64| -> Result<Vec<Var>, &str> {
+                    let mut newset = a.clone();
+                    newset.push(Var::Linear(b));
+                    Ok(newset)
+                }),
+
+                (Set, Gestalt, Set|a: Vec<Var>, b: Vec<u8>| -> Result<Vec<Var>, &str> {
+                    let mut newset = a.clone();
+                    newset.push(Var::Gestalt(b));
+                    Ok(newset)
+                }),
+
+                (Set, Set, Set|a: Vec<Var>, b: Vec<Var>| -> Result<Vec<Var>, &str> {
+                    let mut newset = a.clone();
+                    newset.push(Var::Set(b));
+                    Ok(newset)
+                })
+            )
+        }
+        //Subtraction
+        b'-' => {
+            multi_operate!(
+                (Linear, Linear, Linear|a: f64, b:f64| -> Result<f64, &str> {Ok(a - b)}),
+
+                (Gestalt, Linear, Gestalt|a: Vec<u8>, b: f64| -> Result<Vec<u8>, &str> {
+                    let mut newges = a.clone();
+                    match resolve_index(b, a.len()) {
+                        Some(i) => {newges.remove(i);}
+                        None => return Err("Removal index out of bounds"),
+                    }
+                    Ok(newges)
+                }),
+
+                (Set, Linear, Set|a: Vec<Var>, b: f64| -> Result<Vec<Var>, &str> {
+                    let mut newset = a.clone();
+                    match resolve_index(b, a.len()) {
+                        Some(i) => {newset.remove(i);}
+                        None => return Err("Removal index out of bounds"),
+                    }
+                    Ok(newset)
+                })
+            )
+        }
+        //Multiplication
+        b'*' => {
+            multi_operate!(
+                (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {Ok(a * b)}),
+
+                //Set concatenation
+                (Set, Set, Set|mut a: Vec<Var>, b: Vec<Var>| -> Result<Vec<Var>, &str> {
+                    for var in b {a.push(var.clone())}
+                    Ok(a)
+                })
+            )
+        }
+        //Division
+        b'/' => {
+            multi_operate!(
+                (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {Ok(a / b)})
+            )
+        }
+        //Exponentiation
+        b'^' => {
+            multi_operate!(
+                (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {Ok(a.powf(b))}),
+
+                //Special cases for giving the length of sets and gestalts
+                (Gestalt, Void, Linear|a: Vec<u8>, _b: ()| -> Result<f64, &str> {Ok(a.len() as f64)}),
+
+                (Set, Void, Linear|a: Vec<Var>, _b: ()| -> Result<f64, &str> {Ok(a.len() as f64)})
+            )
+        }
+
+        //LOGICAL
+
+        //And
+        b'&' => Ok(Var::Linear(if a.truthy() && b.truthy() {
+            1.0
+        } else {
+            0.0
+        })),
+        //Or
+        b'|' => Ok(Var::Linear(if a.truthy() || b.truthy() {
+            1.0
+        } else {
+            0.0
+        })),
+        //Exclusive or
+        b'X' => Ok(Var::Linear(if a.truthy() != b.truthy() {
+            1.0
+        } else {
+            0.0
+        })),
+        //Not, only looks at its primary argument
+        b'N' => Ok(Var::Linear(if a.truthy() { 0.0 } else { 1.0 })),
+
+        //COMPARISON
+
+        //Equal to, values of different types are never equal
+        b'=' => Ok(Var::Linear(if a == b { 1.0 } else { 0.0 })),
+        //Not equal to
+        b'Q' => Ok(Var::Linear(if a != b { 1.0 } else { 0.0 })),
+        //Ordering, for Linears, Gestalts and Sets alike
+        b'O' => match a.order(b) {
+            Ok(ordering) => Ok(Var::Linear(ordering as i8 as f64)),
+            Err(error) => Err(error.to_string()),
+        },
+        //Greater than
+        b'>' => {
+            multi_operate!(
+                (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {
+                    if a > b {Ok(1.0)} else {Ok(0.0)}
+                }),
+
+                (Gestalt, Linear, Gestalt|a: Vec<u8>, b: f64| -> Result<Vec<u8>, &str> {
+                    if b < 0.0 || b > a.len() as f64 {
+                        return Err("Cannot remove more characters than the Gestalt has");
+                    }
+                    let mut newges = a.clone();
+                    newges.truncate(a.len() - b as usize);
+                    Ok(newges)
+                }),
+
+                (Set, Linear, Set|a: Vec<Var>, b: f64| -> Result<Vec<Var>, &str> {
+                    if b < 0.0 || b > a.len() as f64 {
+                        return Err("Cannot remove more elements than the Set has");
+                    }
+                    let mut newset = a.clone();
+                    newset.truncate(a.len() - b as usize);
+                    Ok(newset)
+                })
+            )
+        }
+        //Less than
+        b'<' => {
+            multi_operate!(
+                (Linear, Linear, Linear|a: f64, b: f64| -> Result<f64, &str> {
+                    if a < b {Ok(1.0)} else {Ok(0.0)}
+                }),
+
+                (Gestalt, Linear, Gestalt|a: Vec<u8>, b: f64| -> Result<Vec<u8>, &str> {
+                    if b < 0.0 || b > a.len() as f64 {
+                        return Err("Cannot remove more characters than the Gestalt has");
+                    }
+                    Ok(a[b as usize..].to_vec())
+                }),
+
+                (Set, Linear, Set|a: Vec<Var>, b: f64| -> Result<Vec<Var>, &str> {
+                    if b < 0.0 || b > a.len() as f64 {
+                        return Err("Cannot remove more elements than the Set has");
+                    }
+                    Ok(a[b as usize..].to_vec())
+                })
+            )
+        }
+
+        //GESTALT
+
+        //Splitting
+        b'S' => {
+            multi_operate!(
+                (Gestalt, Gestalt, Set|a: Vec<u8>, b: Vec<u8>| -> Result<Vec<Var>, &str> {
+                    if b.is_empty() {
+                        return Err("Empty delimiter given to split");
+                    }
+
+                    Ok(split_subslice(&a, &b).into_iter().map(Var::Gestalt).collect())
+                })
+            )
+        }
+        //Joining
+        b'J' => {
+            multi_operate!(
+                (Set, Gestalt, Gestalt|a: Vec<Var>, b: Vec<u8>| -> Result<Vec<u8>, &str> {
+                    let mut joined: Vec<u8> = Vec::new();
+
+                    for (i, var) in a.into_iter().enumerate() {
+                        if i != 0 {
+                            joined.extend_from_slice(&b);
+                        }
+
+                        match var {
+                            Var::Gestalt(g) => joined.extend(g),
+                            Var::Linear(l) => joined.extend(format!("{}", l).into_bytes()),
+                            _ => return Err("Only Gestalts and Linears can be joined"),
+                        }
+                    }
+
+                    Ok(joined)
+                })
+            )
+        }
+        //Finding
+        b'F' => {
+            multi_operate!(
+                (Gestalt, Gestalt, Linear|a: Vec<u8>, b: Vec<u8>| -> Result<f64, &str> {
+                    match find_subslice(&a, &b) {
+                        Some(i) => Ok(i as f64),
+                        None => Ok(-1.0),
+                    }
+                })
+            )
+        }
+        //Replacing
+        b'R' => {
+            multi_operate!(
+                (Gestalt, Set, Gestalt|a: Vec<u8>, b: Vec<Var>| -> Result<Vec<u8>, &str> {
+                    let (from, to) = match b.as_slice() {
+                        [Var::Gestalt(from), Var::Gestalt(to)] => (from, to),
+                        _ => return Err("Replacement must be a Set of two Gestalts"),
+                    };
+
+                    if from.is_empty() {
+                        return Err("Empty pattern given to replace");
+                    }
+
+                    Ok(split_subslice(&a, from).join(to.as_slice()))
+                })
+            )
+        }
+        //Uppercasing
+        b'U' => {
+            multi_operate!(
+                (Gestalt, Void, Gestalt|a: Vec<u8>, _b: ()| -> Result<Vec<u8>, &str> {
+                    Ok(cstring_from_utf8!(a).to_uppercase().into())
+                })
+            )
+        }
+        //Lowercasing
+        b'L' => {
+            multi_operate!(
+                (Gestalt, Void, Gestalt|a: Vec<u8>, _b: ()| -> Result<Vec<u8>, &str> {
+                    Ok(cstring_from_utf8!(a).to_lowercase().into())
+                })
+            )
+        }
+        //Whitespace trimming
+        b'T' => {
+            multi_operate!(
+                (Gestalt, Void, Gestalt|a: Vec<u8>, _b: ()| -> Result<Vec<u8>, &str> {
+                    Ok(cstring_from_utf8!(a).trim().into())
+                })
+            )
+        }
+        //Starts with (head)
+        b'H' => {
+            multi_operate!(
+                (Gestalt, Gestalt, Linear|a: Vec<u8>, b: Vec<u8>| -> Result<f64, &str> {
+                    if a.starts_with(&b) {Ok(1.0)} else {Ok(0.0)}
+                })
+            )
+        }
+        //Ends with
+        b'E' => {
+            multi_operate!(
+                (Gestalt, Gestalt, Linear|a: Vec<u8>, b: Vec<u8>| -> Result<f64, &str> {
+                    if a.ends_with(&b) {Ok(1.0)} else {Ok(0.0)}
+                })
+            )
+        }
+        //Decimal formatting
+        b'D' => {
+            multi_operate!(
+                (Linear, Linear, Gestalt|a: f64, b: f64| -> Result<Vec<u8>, &str> {
+                    if b < 0.0 {
+                        return Err("Negative precision given to decimal formatting");
+                    }
+
+                    //Past this nothing but zeroes (or a huge allocation) could come out
+                    if b > 1100.0 {
+                        return Err("Precision too large for decimal formatting");
+                    }
+
+                    Ok(format!("{:.*}", b as usize, a).into())
+                })
+            )
+        }
+
+        //TYPES
+
+        //Typeof, works on every type (including Void) so the macro can't cover it
+        b'Y' => Ok(Var::Gestalt(a.type_name().into())),
+        //Explicit conversion
+        b'V' => match b {
+            Var::Gestalt(g) => a
+                .convert(cstring_from_utf8!(g.to_vec()).as_str())
+                .map_err(String::from),
+
+            _ => Err("Invalid operand types".to_string()),
+        },
+        //Safe parsing, gives back a Void rather than an error if the Gestalt isn't a number
+        b'P' => match a {
+            Var::Gestalt(_) | Var::Linear(_) => Ok(a.convert("Linear").unwrap_or(Var::void())),
+            _ => Ok(Var::void()),
+        },
+
+        //Byte conversion, between a Gestalt and a Set of its bytes as Linears
+        b'B' => {
+            multi_operate!(
+                (Gestalt, Void, Set|a: Vec<u8>, _b: ()| -> Result<Vec<Var>, &str> {
+                    Ok(a.into_iter().map(|byte| Var::Linear(byte as f64)).collect())
+                }),
+
+                (Set, Void, Gestalt|a: Vec<Var>, _b: ()| -> Result<Vec<u8>, &str> {
+                    a.into_iter().map(|var| match var {
+                        Var::Linear(l) if l.fract() == 0.0 && (0.0..=255.0).contains(&l) => Ok(l as u8),
+                        _ => Err("Bytes must be whole Linears from 0 to 255"),
+                    }).collect()
+                })
+            )
+        }
+
+        //JSON
+
+        //Parsing JSON from a Gestalt, objects come out as Sets of [key, value] pairs
+        b'A' => match (a, b) {
+            (Var::Gestalt(g), Var::Void(_)) => json::parse(g),
+            _ => Err("Invalid operand types".to_string()),
+        },
+        //Writing any var out as JSON, a truthy secondary argument writes Sets of [key, value] pairs as objects
+        b'G' => json::stringify(a, b.truthy()).map(Var::Gestalt),
+
+        //SLICING
+
+        //Slicing (cut)
+        b'C' => {
+            multi_operate!(
+                (Gestalt, Set, Gestalt|a: Vec<u8>, b: Vec<Var>| -> Result<Vec<u8>, &str> {
+                    let (start, end) = resolve_range(&b, a.len())?;
+                    Ok(a[start..end].to_vec())
+                }),
+
+                (Set, Set, Set|a: Vec<Var>, b: Vec<Var>| -> Result<Vec<Var>, &str> {
+                    let (start, end) = resolve_range(&b, a.len())?;
+                    Ok(a[start..end].to_vec())
+                })
+            )
+        }
+        //Set at index (write)
+        b'W' => {
+            multi_operate!(
+                (Gestalt, Set, Gestalt|a: Vec<u8>, b: Vec<Var>| -> Result<Vec<u8>, &str> {
+                    let (index, value) = unpack_index_pair(&b)?;
+                    let i = resolve_index(index, a.len()).ok_or("Write index out of bounds")?;
+                    let mut newges = a.clone();
+                    match value {
+                        Var::Gestalt(g) => {newges.splice(i..i + 1, g);}
+                        _ => return Err("Only Gestalts can be written into a Gestalt"),
+                    }
+                    Ok(newges)
+                }),
+
+                (Set, Set, Set|a: Vec<Var>, b: Vec<Var>| -> Result<Vec<Var>, &str> {
+                    let (index, value) = unpack_index_pair(&b)?;
+                    let i = resolve_index(index, a.len()).ok_or("Write index out of bounds")?;
+                    let mut newset = a.clone();
+                    newset[i] = value;
+                    Ok(newset)
+                })
+            )
+        }
+        //Insertion
+        b'I' => {
+            multi_operate!(
+                (Gestalt, Set, Gestalt|a: Vec<u8>, b: Vec<Var>| -> Result<Vec<u8>, &str> {
+                    let (index, value) = unpack_index_pair(&b)?;
+                    let i = resolve_bound(index, a.len()).ok_or("Insertion index out of bounds")?;
+                    let mut newges = a.clone();
+                    match value {
+                        Var::Gestalt(g) => {newges.splice(i..i, g);}
+                        _ => return Err("Only Gestalts can be inserted into a Gestalt"),
+                    }
+                    Ok(newges)
+                }),
+
+                (Set, Set, Set|a: Vec<Var>, b: Vec<Var>| -> Result<Vec<Var>, &str> {
+                    let (index, value) = unpack_index_pair(&b)?;
+                    let i = resolve_bound(index, a.len()).ok_or("Insertion index out of bounds")?;
+                    let mut newset = a.clone();
+                    newset.insert(i, value);
+                    Ok(newset)
+                })
+            )
+        }
+
+        //Set & gestalt indexing, macro can't cover these subtypeless sets so its got its own special thingy
+        b'`' => match (a, b) {
+            (Var::Set(s), Var::Linear(l)) => match resolve_index(*l, s.len()) {
+                Some(i) => Ok(s[i].clone()),
+                _ => Err(format!("Could not get index {} from Set", l.floor())),
+            },
+
+            (Var::Gestalt(g), Var::Linear(l)) => match resolve_index(*l, g.len()) {
+                Some(i) => Ok(Var::Gestalt(vec![g[i]])),
+                _ => Err(format!("Could not get index {} from Gestalt", l.floor())),
+            },
+
+            //Special modulus functionality
+            (Var::Linear(a), Var::Linear(b)) => Ok(Var::Linear(a % b)),
+
+            _ => Err("Invalid types for operator".to_string()),
+        },
+
+        //TESTING
+
+        //Asserting, a is a Set of the value given and the value expected, b is a message to fail with (or a Void)
+        b'a' => {
+            let message = match b {
+                Var::Gestalt(g) => format!(": {}", String::from_utf8_lossy(g)),
+                Var::Void(_) => String::new(),
+                _ => return Err("Invalid operand types".to_string()),
+            };
+
+            match a {
+                Var::Set(pair) => match pair.as_slice() {
+                    [given, expected] if given == expected => Ok(Var::void()),
+                    [given, expected] => Err(format!(
+                        "Assertion failed{} (expected {}, got {})",
+                        message,
+                        expected.to_qrt_literal(),
+                        given.to_qrt_literal()
+                    )),
+                    _ => Err(
+                        "Assertion needs a Set of the value given and the value expected"
+                            .to_string(),
+                    ),
+                },
+                _ => Err("Invalid operand types".to_string()),
+            }
+        }
+
+        //Invalid operator
+        _ => Err("Invalid operator".to_string()),
+    }
+}
@@ -0,0 +1,346 @@
+use super::{
+    interpreter::{Hook, Step},
+    structs::*,
+    syntax::{parse, Expression},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+//How long was spent somewhere, and how many times it was reached
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cost {
+    pub time: Duration,
+    pub count: usize,
+}
+
+//An evaluation the profiler is within, with the loops running in it (outermost first)
+struct Frame {
+    origin: Option<usize>,
+    name: String,
+    loops: Vec<(usize, String)>,
+}
+
+//Where a step's time goes: its folded stack, and the jumps and loops it's within (each counted once).
+//It only changes when the frames do, so it's worked out once for every step taken within the same ones.
+struct Within {
+    stack: String,
+    jumps: Vec<String>,
+    loops: Vec<String>,
+}
+
+//Times every step of evaluation, adding it up per source line, per jump definition, and per loop.
+//Steps take the time until the next step, so finish has to be called once evaluation is done to time the last one.
+//  lines counts the steps taken on each line (None being Gestalts evaluated as code)
+//  jumps counts the times each jump was evaluated, and its time includes every jump it evaluated
+//  loops counts the times each loop was started, and its time includes every iteration
+//  stacks is the time spent in every nesting of jumps and loops, the way flamegraphs take them
+pub struct Profiler {
+    pub lines: BTreeMap<Option<usize>, Cost>,
+    pub jumps: BTreeMap<String, Cost>,
+    pub loops: BTreeMap<String, Cost>,
+    pub stacks: BTreeMap<String, Duration>,
+    program: Vec<u8>,
+    line_starts: Vec<usize>,
+    names: HashMap<usize, String>,
+    frames: Vec<Frame>,
+    within: Option<Within>,
+    last: Option<(Instant, Option<usize>)>,
+}
+
+impl Profiler {
+    //A profiler for the given program. Jumps and loops are named from its definitions, if it can be parsed.
+    pub fn new(program: &[u8]) -> Profiler {
+        let line_starts = line_starts(program);
+        let mut names = HashMap::new();
+
+        if let Ok(block) = parse(program) {
+            block.walk(&mut |node| {
+                if let Expression::Named(operator @ (b':' | b'~'), name, body) = &node.expression {
+                    //Evaluation knows jumps and loops by where their code starts, just after the bracket
+                    let line = line(&line_starts, node.span.start);
+                    names.insert(
+                        body.span.start + 1,
+                        format!(
+                            "{}{} (line {})",
+                            *operator as char,
+                            String::from_utf8_lossy(name),
+                            line
+                        ),
+                    );
+                }
+            });
+        }
+
+        Profiler {
+            lines: BTreeMap::new(),
+            jumps: BTreeMap::new(),
+            loops: BTreeMap::new(),
+            stacks: BTreeMap::new(),
+            program: program.to_vec(),
+            line_starts,
+            names,
+            frames: Vec::new(),
+            within: None,
+            last: None,
+        }
+    }
+
+    //The name of the jump or loop whose code starts at the given position
+    fn name(&self, operator: char, at: Option<usize>) -> String {
+        match at {
+            Some(at) => {
+                self.names.get(&at).cloned().unwrap_or_else(|| {
+                    format!("{}? (line {})", operator, line(&self.line_starts, at))
+                })
+            }
+            None => format!("{}? (Gestalt code)", operator),
+        }
+    }
+
+    //Gives the time since the last step to it, and to everything it was within
+    fn take_time(&mut self, now: Instant) {
+        let (then, line) = match self.last.take() {
+            Some(last) => last,
+            None => return,
+        };
+        let time = now - then;
+
+        self.lines.entry(line).or_default().time += time;
+
+        let within = self.within.get_or_insert_with(|| within(&self.frames));
+
+        for name in &within.jumps {
+            add_time(&mut self.jumps, name, time);
+        }
+        for name in &within.loops {
+            add_time(&mut self.loops, name, time);
+        }
+        match self.stacks.get_mut(&within.stack) {
+            Some(total) => *total += time,
+            None => {
+                self.stacks.insert(within.stack.clone(), time);
+            }
+        }
+    }
+
+    //Times the last step, once evaluation has finished
+    pub fn finish(&mut self) {
+        self.take_time(Instant::now());
+    }
+
+    //Every line, jump, and loop that was reached, the slowest first
+    pub fn table(&self) -> String {
+        let total: Duration = self.lines.values().map(|cost| cost.time).sum();
+        let mut table = format!("Total time: {}\n", milliseconds(total));
+
+        let lines: Vec<(String, Cost)> = self
+            .lines
+            .iter()
+            .map(|(line, cost)| {
+                let name = match line {
+                    Some(line) => {
+                        let text = self.program.split(|c| *c == b'\n').nth(line - 1);
+                        format!(
+                            "{:>5} | {}",
+                            line,
+                            String::from_utf8_lossy(text.unwrap_or_default()).trim()
+                        )
+                    }
+                    None => "Gestalt code".to_string(),
+                };
+                (name, *cost)
+            })
+            .collect();
+
+        for (title, counted, mut costs) in [
+            ("Lines", "steps", lines),
+            ("Jumps", "calls", to_rows(&self.jumps)),
+            ("Loops", "starts", to_rows(&self.loops)),
+        ] {
+            if costs.is_empty() {
+                continue;
+            }
+
+            table += &format!("\n{}:\n{:>12} {:>7} {:>9}\n", title, "time", "%", counted);
+
+            costs.sort_by(|a, b| b.1.time.cmp(&a.1.time).then_with(|| a.0.cmp(&b.0)));
+
+            for (name, cost) in costs {
+                table += &format!(
+                    "{:>12} {:>6.1}% {:>9}  {}\n",
+                    milliseconds(cost.time),
+                    percentage(cost.time, total),
+                    cost.count,
+                    name
+                );
+            }
+        }
+
+        table
+    }
+
+    //The time spent in every nesting of jumps and loops in microseconds, as folded stacks for flamegraph tools
+    pub fn folded(&self) -> String {
+        let mut folded = String::new();
+
+        for (stack, time) in &self.stacks {
+            if time.as_micros() > 0 {
+                folded += &format!("{} {}\n", stack, time.as_micros());
+            }
+        }
+
+        folded
+    }
+}
+
+impl Hook for Profiler {
+    fn step(&mut self, step: &Step) -> Result<(), String> {
+        self.take_time(Instant::now());
+
+        //Leaving evaluations, and entering a new one (jumps and Gestalts get their own evaluation)
+        if self.frames.len() > step.depth + 1 {
+            self.frames.truncate(step.depth + 1);
+            self.within = None;
+        }
+        if self.frames.len() <= step.depth || self.frames[step.depth].origin != step.origin {
+            let name = match (step.depth, step.origin) {
+                (0, _) => "main".to_string(),
+                (_, Some(origin)) => self.name(':', Some(origin)),
+                (_, None) => "Gestalt code".to_string(),
+            };
+
+            if step.depth > 0 && step.origin.is_some() {
+                self.jumps.entry(name.clone()).or_default().count += 1;
+            }
+
+            self.frames.truncate(step.depth);
+            self.frames.push(Frame {
+                origin: step.origin,
+                name,
+                loops: Vec::new(),
+            });
+            self.within = None;
+        }
+
+        //Running loops are kept on the stack, just under the position their code starts at
+        let mut loops = Vec::new();
+        for (i, element) in step.stack.iter().enumerate().rev() {
+            if let (Abstract::Loop(_), Some(Abstract::Var(Var::Linear(start)))) =
+                (element, i.checked_sub(1).and_then(|i| step.stack.get(i)))
+            {
+                let start = *start as usize;
+                let name = match self.frames[step.depth]
+                    .loops
+                    .iter()
+                    .find(|(at, _)| *at == start)
+                {
+                    Some((_, name)) => name.clone(),
+                    None => {
+                        let name = self.name('~', step.origin.map(|origin| origin + start));
+                        self.loops.entry(name.clone()).or_default().count += 1;
+                        name
+                    }
+                };
+
+                loops.push((start, name));
+            }
+        }
+        if self.frames[step.depth].loops != loops {
+            self.frames[step.depth].loops = loops;
+            self.within = None;
+        }
+
+        let line = step
+            .origin
+            .map(|origin| line(&self.line_starts, origin + step.on));
+        self.lines.entry(line).or_default().count += 1;
+
+        //Started after everything above, so the profiler's own work isn't counted
+        self.last = Some((Instant::now(), line));
+        Ok(())
+    }
+}
+
+//Where the step's time goes, for the frames it was taken within
+fn within(frames: &[Frame]) -> Within {
+    let mut stack: Vec<&str> = Vec::new();
+    let mut jumps: Vec<String> = Vec::new();
+    let mut loops: Vec<String> = Vec::new();
+
+    for (depth, frame) in frames.iter().enumerate() {
+        stack.push(&frame.name);
+
+        //Recursion is only counted once, so nothing is given more time than was spent
+        if depth > 0 && frame.origin.is_some() && !jumps.contains(&frame.name) {
+            jumps.push(frame.name.clone());
+        }
+
+        for (_, name) in &frame.loops {
+            stack.push(name);
+
+            if !loops.contains(name) {
+                loops.push(name.clone());
+            }
+        }
+    }
+
+    Within {
+        stack: stack.join(";"),
+        jumps,
+        loops,
+    }
+}
+
+fn add_time(costs: &mut BTreeMap<String, Cost>, name: &str, time: Duration) {
+    match costs.get_mut(name) {
+        Some(cost) => cost.time += time,
+        None => {
+            costs.insert(
+                name.to_string(),
+                Cost {
+                    time,
+                    ..Cost::default()
+                },
+            );
+        }
+    }
+}
+
+//Where every line starts, so the line a position is on can be found without going through the whole program
+fn line_starts(program: &[u8]) -> Vec<usize> {
+    let mut starts = vec![0];
+    starts.extend(
+        program
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c == b'\n')
+            .map(|(i, _)| i + 1),
+    );
+    starts
+}
+
+//The line (counted from 1) the position is on
+fn line(line_starts: &[usize], at: usize) -> usize {
+    line_starts.partition_point(|start| *start <= at)
+}
+
+fn to_rows(costs: &BTreeMap<String, Cost>) -> Vec<(String, Cost)> {
+    costs
+        .iter()
+        .map(|(name, cost)| (name.clone(), *cost))
+        .collect()
+}
+
+fn milliseconds(time: Duration) -> String {
+    format!("{:.3}ms", time.as_secs_f64() * 1000.0)
+}
+
+fn percentage(time: Duration, total: Duration) -> f64 {
+    if total.is_zero() {
+        0.0
+    } else {
+        time.as_secs_f64() / total.as_secs_f64() * 100.0
+    }
+}
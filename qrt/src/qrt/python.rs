@@ -0,0 +1,106 @@
+use super::{evaluate::Task, interpreter::Interpreter, structs::Var, syntax::line_column};
+
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyTypeError, PyValueError},
+    prelude::*,
+    types::{PyBytes, PyFloat, PyList, PyString, PyTuple},
+};
+
+//The Python module (built with the python feature), which is imported as qrt:
+//  evaluate(source, input=None) evaluates the source (a str or bytes) with the input, and gives back what it returns
+//Values are converted both ways: float (or int or bool) and Linear, str or bytes and Gestalt, list (or tuple) and
+//Set, and None and Void. Gestalts holding valid UTF-8 become a str, and any others become bytes.
+
+create_exception!(
+    qrt,
+    QrtError,
+    PyException,
+    "An error thrown by QRT code, with its message and the line and column (counted from 1) it was thrown at"
+);
+
+#[pymodule]
+fn qrt(module: &Bound<PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(evaluate, module)?)?;
+    module.add("QrtError", module.py().get_type::<QrtError>())?;
+    Ok(())
+}
+
+//Evaluates QRT code attached to the real console, releasing the GIL while it runs
+#[pyfunction]
+#[pyo3(signature = (source, input = None))]
+pub fn evaluate(
+    py: Python,
+    source: &Bound<PyAny>,
+    input: Option<&Bound<PyAny>>,
+) -> PyResult<Py<PyAny>> {
+    let program = match from_python(source) {
+        Ok(Var::Gestalt(program)) => program,
+        _ => return Err(PyTypeError::new_err("QRT source has to be a str or bytes")),
+    };
+    let input = match input {
+        Some(input) => from_python(input)?,
+        None => Var::void(),
+    };
+
+    let mut task = Task::new(&program, &input);
+
+    match py.detach(|| task.finish(&mut Interpreter::new())) {
+        Ok(result) => Ok(to_python(py, &result)?.unbind()),
+        Err((msg, on, ..)) => {
+            let (line, column) = line_column(&program, task.thrown_at().unwrap_or(on));
+
+            let error = QrtError::new_err(msg.clone());
+            let value = error.value(py);
+            value.setattr("message", msg)?;
+            value.setattr("line", line + 1)?;
+            value.setattr("column", column + 1)?;
+
+            Err(error)
+        }
+    }
+}
+
+pub fn from_python(object: &Bound<PyAny>) -> PyResult<Var> {
+    if object.is_none() {
+        Ok(Var::void())
+    } else if let Ok(string) = object.cast::<PyString>() {
+        Ok(Var::Gestalt(string.to_str()?.as_bytes().to_vec()))
+    } else if let Ok(bytes) = object.cast::<PyBytes>() {
+        Ok(Var::Gestalt(bytes.as_bytes().to_vec()))
+    } else if object.is_instance_of::<PyList>() || object.is_instance_of::<PyTuple>() {
+        Ok(Var::Set(
+            object
+                .try_iter()?
+                .map(|element| from_python(&element?))
+                .collect::<PyResult<Vec<Var>>>()?,
+        ))
+    } else if let Ok(linear) = object.extract::<f64>() {
+        Ok(Var::Linear(linear))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "QRT has no type for a {}",
+            object.get_type().name()?
+        )))
+    }
+}
+
+pub fn to_python<'py>(py: Python<'py>, var: &Var) -> PyResult<Bound<'py, PyAny>> {
+    match var {
+        Var::Void(_) => Ok(py.None().into_bound(py)),
+        Var::Linear(linear) => Ok(PyFloat::new(py, *linear).into_any()),
+        Var::Gestalt(gestalt) => match core::str::from_utf8(gestalt) {
+            Ok(string) => Ok(PyString::new(py, string).into_any()),
+            Err(_) => Ok(PyBytes::new(py, gestalt).into_any()),
+        },
+        Var::Set(set) => Ok(PyList::new(
+            py,
+            set.iter()
+                .map(|var| to_python(py, var))
+                .collect::<PyResult<Vec<_>>>()?,
+        )?
+        .into_any()),
+        //KillIDs only mean something to the loop that is running, so they can't leave the program
+        Var::Kill(_) => Err(PyValueError::new_err("KillIDs cannot be given to Python")),
+    }
+}
@@ -0,0 +1,110 @@
+use super::{
+    evaluate::{evaluate_from, evaluate_in},
+    interpreter::{Hook, Interpreter},
+    io::{Io, StdIo},
+    structs::*,
+};
+
+use std::collections::{HashMap, VecDeque};
+
+//Runs one piece of QRT code after another as though they were a single program, so aliases and jumps defined by one
+//are there for the next, and for the Rust code running them to read, write and call.
+//Every piece of code run is kept, one after another on their own lines, since jumps are where their code is within it.
+//Positions given with errors are within all of that code, which source gives.
+pub struct Session<I: Io = StdIo, H: Hook = ()> {
+    pub interpreter: Interpreter<I, H>,
+    source: Vec<u8>,
+    scope: Scope,
+}
+
+impl Session<StdIo> {
+    //A session attached to the real console
+    pub fn new() -> Session<StdIo> {
+        Session::with_interpreter(Interpreter::new())
+    }
+}
+
+impl Default for Session<StdIo> {
+    fn default() -> Session<StdIo> {
+        Session::new()
+    }
+}
+
+impl<I: Io, H: Hook> Session<I, H> {
+    //A session running code within the given interpreter, such as one with host functions registered on it
+    pub fn with_interpreter(interpreter: Interpreter<I, H>) -> Session<I, H> {
+        Session {
+            interpreter,
+            source: Vec::new(),
+            scope: Scope::default(),
+        }
+    }
+
+    //Runs the code as the next part of the session's program, with everything defined so far still defined.
+    //Anything it defines is kept, even when it throws an error partway through.
+    pub fn run(&mut self, program: &[u8], input: &Var) -> Evaluation {
+        if !self.source.is_empty() {
+            self.source.push(b'\n');
+        }
+        let start = self.source.len();
+        self.source.extend_from_slice(program);
+
+        evaluate_in(
+            &mut self.interpreter,
+            &self.source,
+            input,
+            &mut self.scope,
+            start,
+            Some(0),
+            0,
+        )
+    }
+
+    //Evaluates the jump defined with the given name, just as !(name!){input} would
+    pub fn call(&mut self, name: &str, input: &Var) -> Evaluation {
+        let jmp = match self.scope.map.get(&(name.to_string() + "!")) {
+            Some(Var::Linear(jmp)) if *jmp >= 0.0 && *jmp <= self.source.len() as f64 => {
+                *jmp as usize
+            }
+            _ => {
+                return Err((
+                    "Jump not found".to_string(),
+                    0,
+                    0,
+                    VecDeque::new(),
+                    self.scope.map.clone(),
+                ))
+            }
+        };
+
+        evaluate_from(
+            &mut self.interpreter,
+            &self.source[jmp..],
+            input,
+            HashMap::new(),
+            0,
+            Some(jmp),
+            1,
+        )
+    }
+
+    //The value of the alias with the given name (jumps having a bang after their name)
+    pub fn get(&self, name: &str) -> Option<&Var> {
+        self.scope.map.get(name)
+    }
+
+    //Gives the alias the value, just as #name{value} would
+    pub fn set(&mut self, name: &str, value: Var) {
+        self.scope.map.insert(name.to_string(), value);
+    }
+
+    //Every alias defined so far
+    pub fn variables(&self) -> &HashMap<String, Var> {
+        &self.scope.map
+    }
+
+    //All of the code run so far
+    pub fn source(&self) -> &[u8] {
+        &self.source
+    }
+}
@@ -0,0 +1,261 @@
+use super::{
+    evaluate::{Frame, Task},
+    structs::*,
+};
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+//Snapshots save everything about where a task's evaluation is, so it can carry on later, even in another process.
+//They're written as a single QRT literal:
+//  ["QRT snapshot", version, [code...], [frame...], scope]
+//  a frame being [code index, start, input, scope, stack, on, origin, depth, returned]
+//  a scope being [kill id of the next loop, [[name, value]...]]
+//Stack elements are written as ["var", value], ["operator", "o"] or ["loop", kill id], and alias values as
+//["var", value] or ["kill", kill id], since KillIDs can't be written as literals.
+//Evaluations share their code when they can, so jumps are saved as part of the code that defined them.
+const VERSION: f64 = 1.0;
+
+//The largest whole number a Linear holds exactly
+const MAX_INDEX: f64 = (1u64 << 53) as f64;
+
+impl Task {
+    //The snapshot of where the task is, which Task::restore carries on from
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut codes: Vec<&Arc<[u8]>> = Vec::new();
+        let mut frames = Vec::new();
+
+        for frame in &self.frames {
+            let index = match codes.iter().position(|code| Arc::ptr_eq(code, &frame.code)) {
+                Some(index) => index,
+                None => {
+                    codes.push(&frame.code);
+                    codes.len() - 1
+                }
+            };
+
+            frames.push(Var::Set(vec![
+                linear(index),
+                linear(frame.start),
+                frame.input.clone(),
+                save_scope(&frame.scope),
+                Var::Set(frame.stack.iter().map(save_abstract).collect()),
+                linear(frame.on),
+                frame.origin.map(linear).unwrap_or_else(Var::void),
+                linear(frame.depth),
+                match &frame.returned {
+                    Some(returned) => Var::Set(vec![returned.clone()]),
+                    None => Var::void(),
+                },
+            ]));
+        }
+
+        Var::Set(vec![
+            Var::Gestalt(b"QRT snapshot".to_vec()),
+            Var::Linear(VERSION),
+            Var::Set(
+                codes
+                    .iter()
+                    .map(|code| Var::Gestalt(code.to_vec()))
+                    .collect(),
+            ),
+            Var::Set(frames),
+            save_scope(&self.scope),
+        ])
+        .to_qrt_literal()
+        .into_bytes()
+    }
+
+    //The task a snapshot was taken of, ready to carry on running
+    pub fn restore(snapshot: &[u8]) -> Result<Task, String> {
+        let snapshot = Var::from_qrt_literal(snapshot)
+            .map_err(|error| format!("Invalid snapshot ({})", error))?;
+
+        let (codes, frames, scope) = match snapshot {
+            Var::Set(parts) => match parts.as_slice() {
+                [Var::Gestalt(title), Var::Linear(version), Var::Set(codes), Var::Set(frames), scope]
+                    if title == b"QRT snapshot" =>
+                {
+                    if *version != VERSION {
+                        return Err(format!("Unsupported snapshot version {}", version));
+                    }
+                    (codes.clone(), frames.clone(), scope.clone())
+                }
+                _ => return Err(invalid("header")),
+            },
+            _ => return Err(invalid("header")),
+        };
+
+        let codes = codes
+            .into_iter()
+            .map(|code| match code {
+                Var::Gestalt(code) => Ok(Arc::from(code)),
+                _ => Err(invalid("code")),
+            })
+            .collect::<Result<Vec<Arc<[u8]>>, String>>()?;
+
+        Ok(Task {
+            frames: frames
+                .iter()
+                .map(|frame| restore_frame(frame, &codes))
+                .collect::<Result<Vec<Frame>, String>>()?,
+            scope: restore_scope(&scope)?,
+            thrown_at: None,
+        })
+    }
+}
+
+fn restore_frame(frame: &Var, codes: &[Arc<[u8]>]) -> Result<Frame, String> {
+    let parts = match frame {
+        Var::Set(parts) if parts.len() == 9 => parts,
+        _ => return Err(invalid("frame")),
+    };
+
+    let code = codes
+        .get(index(&parts[0])?)
+        .ok_or_else(|| invalid("frame's code"))?;
+
+    let start = index(&parts[1])?;
+    if start > code.len() {
+        return Err(invalid("frame's start"));
+    }
+
+    let on = index(&parts[5])?;
+    if on > code.len() - start {
+        return Err(invalid("frame's position"));
+    }
+
+    let stack = match &parts[4] {
+        Var::Set(stack) => stack
+            .iter()
+            .map(restore_abstract)
+            .collect::<Result<VecDeque<Abstract>, String>>()?,
+        _ => return Err(invalid("frame's stack")),
+    };
+
+    Ok(Frame {
+        code: code.clone(),
+        start,
+        input: parts[2].clone(),
+        scope: restore_scope(&parts[3])?,
+        stack,
+        on,
+        origin: match &parts[6] {
+            Var::Void(_) => None,
+            origin => Some(index(origin)?),
+        },
+        depth: index(&parts[7])?,
+        returned: match &parts[8] {
+            Var::Void(_) => None,
+            Var::Set(returned) if returned.len() == 1 => Some(returned[0].clone()),
+            _ => return Err(invalid("frame's returned value")),
+        },
+    })
+}
+
+fn save_scope(scope: &Scope) -> Var {
+    //Aliases are sorted, so the same scope is always saved the same way
+    let mut map: Vec<(&String, &Var)> = scope.map.iter().collect();
+    map.sort_by(|a, b| a.0.cmp(b.0));
+
+    Var::Set(vec![
+        linear(scope.killidon),
+        Var::Set(
+            map.into_iter()
+                .map(|(name, value)| {
+                    let value = match value {
+                        Var::Kill(killid) => tagged("kill", linear(*killid)),
+                        value => tagged("var", value.clone()),
+                    };
+                    Var::Set(vec![Var::Gestalt(name.as_bytes().to_vec()), value])
+                })
+                .collect(),
+        ),
+    ])
+}
+
+fn restore_scope(scope: &Var) -> Result<Scope, String> {
+    let (killidon, aliases) = match scope {
+        Var::Set(parts) => match parts.as_slice() {
+            [killidon, Var::Set(aliases)] => (index(killidon)?, aliases),
+            _ => return Err(invalid("scope")),
+        },
+        _ => return Err(invalid("scope")),
+    };
+
+    let mut map = HashMap::new();
+    for alias in aliases {
+        let (name, value) = match alias {
+            Var::Set(pair) => match pair.as_slice() {
+                [Var::Gestalt(name), value] => (name, value),
+                _ => return Err(invalid("alias")),
+            },
+            _ => return Err(invalid("alias")),
+        };
+
+        let value = match untag(value)? {
+            ("kill", killid) => Var::Kill(index(killid)?),
+            ("var", value) => value.clone(),
+            _ => return Err(invalid("alias")),
+        };
+
+        map.insert(String::from_utf8_lossy(name).into_owned(), value);
+    }
+
+    Ok(Scope { map, killidon })
+}
+
+fn save_abstract(element: &Abstract) -> Var {
+    match element {
+        Abstract::Var(var) => tagged("var", var.clone()),
+        Abstract::Operator(operator) => tagged("operator", Var::Gestalt(vec![*operator])),
+        Abstract::Loop(killid) => tagged("loop", linear(*killid)),
+    }
+}
+
+fn restore_abstract(element: &Var) -> Result<Abstract, String> {
+    match untag(element)? {
+        ("var", var) => Ok(Abstract::Var(var.clone())),
+        ("operator", Var::Gestalt(operator)) if operator.len() == 1 => {
+            Ok(Abstract::Operator(operator[0]))
+        }
+        ("loop", killid) => Ok(Abstract::Loop(index(killid)?)),
+        _ => Err(invalid("stack element")),
+    }
+}
+
+fn tagged(tag: &str, var: Var) -> Var {
+    Var::Set(vec![Var::Gestalt(tag.as_bytes().to_vec()), var])
+}
+
+fn untag(var: &Var) -> Result<(&str, &Var), String> {
+    match var {
+        Var::Set(pair) => match pair.as_slice() {
+            [Var::Gestalt(tag), var] => Ok((core::str::from_utf8(tag).unwrap_or_default(), var)),
+            _ => Err(invalid("value")),
+        },
+        _ => Err(invalid("value")),
+    }
+}
+
+fn linear(number: usize) -> Var {
+    Var::Linear(number as f64)
+}
+
+//A position, count or kill id, which can only be a whole number. They're never more than a Linear holds exactly,
+//so evaluation can still count up from them.
+fn index(var: &Var) -> Result<usize, String> {
+    match var {
+        Var::Linear(number) if *number >= 0.0 && number.fract() == 0.0 && *number <= MAX_INDEX => {
+            Ok(*number as usize)
+        }
+        _ => Err(invalid("number")),
+    }
+}
+
+//The error for a snapshot with a part that isn't what it should be
+fn invalid(part: &str) -> String {
+    format!("Invalid snapshot ({})", part)
+}
@@ -1,4 +1,8 @@
-use std::collections::{HashMap, VecDeque};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, VecDeque},
+    fmt,
+};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Var {
@@ -16,15 +20,17 @@ impl Var {
 
             Var::Linear(l) => f64::to_string(l),
 
-            //Unwrap is fine here since getting the vec8 into the gestalt in the first place in QRT code should ensure validity
-            Var::Gestalt(g) => "\"".to_string() + core::str::from_utf8(g).unwrap() + "\"",
+            //Lossy since Gestalts can hold any bytes (a program file need not be valid UTF-8)
+            Var::Gestalt(g) => "\"".to_string() + &String::from_utf8_lossy(g) + "\"",
 
             Var::Set(set) => {
                 let mut string: String = "[".to_string();
 
-                for var in set {
+                for (i, var) in set.iter().enumerate() {
+                    if i > 0 {
+                        string.push_str(", ");
+                    }
                     string.push_str(&var.represent());
-                    string.push_str(", ");
                 }
 
                 string.push(']');
@@ -42,6 +48,100 @@ impl Var {
     pub fn void() -> Var {
         Var::Void(())
     }
+
+    //The name of the variant, as given to QRT code by typeof and taken by conversion
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Var::Void(_) => "Void",
+            Var::Linear(_) => "Linear",
+            Var::Gestalt(_) => "Gestalt",
+            Var::Set(_) => "Set",
+            Var::Kill(_) => "Kill",
+        }
+    }
+
+    //Whether a var counts as true for the logical operators. Linears are true above 0 (like conditionals),
+    //Gestalts and Sets are true when they aren't empty, Voids are always false.
+    pub fn truthy(&self) -> bool {
+        match self {
+            Var::Void(_) => false,
+            Var::Linear(l) => *l > 0.0,
+            Var::Gestalt(g) => !g.is_empty(),
+            Var::Set(set) => !set.is_empty(),
+            Var::Kill(_) => true,
+        }
+    }
+
+    //Orders two vars of the same type, Gestalts by their bytes and Sets element by element (lexicographically).
+    //Throws an error for vars of different types, or Linears that can't be ordered (NaN).
+    pub fn order(&self, other: &Var) -> Result<Ordering, &'static str> {
+        match (self, other) {
+            (Var::Void(_), Var::Void(_)) => Ok(Ordering::Equal),
+            (Var::Linear(a), Var::Linear(b)) => a.partial_cmp(b).ok_or("Cannot order NaN"),
+            (Var::Gestalt(a), Var::Gestalt(b)) => Ok(a.cmp(b)),
+            (Var::Kill(a), Var::Kill(b)) => Ok(a.cmp(b)),
+
+            (Var::Set(a), Var::Set(b)) => {
+                for (a, b) in a.iter().zip(b) {
+                    match a.order(b)? {
+                        Ordering::Equal => (),
+                        ordering => return Ok(ordering),
+                    }
+                }
+
+                Ok(a.len().cmp(&b.len()))
+            }
+
+            _ => Err("Cannot order values of different types"),
+        }
+    }
+
+    //Explicitly converts a var to the variant with the given name, erroring if there is no sensible conversion.
+    //Gestalts convert to and from Sets of single character Gestalts.
+    pub fn convert(&self, to: &str) -> Result<Var, &'static str> {
+        match (self, to) {
+            (Var::Void(_), _) | (_, "Void") => Ok(Var::void()),
+
+            (Var::Linear(_), "Linear") | (Var::Gestalt(_), "Gestalt") | (Var::Set(_), "Set") => {
+                Ok(self.clone())
+            }
+
+            (Var::Gestalt(g), "Linear") => match core::str::from_utf8(g).map(str::parse::<f64>) {
+                Ok(Ok(l)) => Ok(Var::Linear(l)),
+                _ => Err("Could not convert Gestalt to Linear"),
+            },
+
+            (Var::Linear(l), "Gestalt") => Ok(Var::Gestalt(format!("{}", l).into())),
+
+            (Var::Set(set), "Gestalt") => {
+                let mut gestalt: Vec<u8> = Vec::new();
+
+                for var in set {
+                    match var {
+                        Var::Gestalt(g) => gestalt.extend_from_slice(g),
+                        _ => return Err("Only Sets of Gestalts can be converted to Gestalt"),
+                    }
+                }
+
+                Ok(Var::Gestalt(gestalt))
+            }
+
+            (Var::Linear(l), "Set") => Ok(Var::Set(vec![Var::Linear(*l)])),
+
+            (Var::Gestalt(g), "Set") => match core::str::from_utf8(g) {
+                Ok(s) => Ok(Var::Set(
+                    s.chars()
+                        .map(|c| Var::Gestalt(c.to_string().into()))
+                        .collect(),
+                )),
+                Err(_) => Err("Invalid Gestalt chars"),
+            },
+
+            (_, "Linear" | "Gestalt" | "Set") => Err("No conversion exists for the given type"),
+
+            _ => Err("Invalid type name given to conversion"),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -56,17 +156,49 @@ impl Abstract {
         match self {
             Abstract::Var(v) => "Var(".to_string() + &v.represent() + ")",
 
-            Abstract::Operator(o) => {
-                let mut buf = vec![0; 1];
-
-                "Operator(".to_string() + (*o as char).encode_utf8(&mut buf) + ")"
-            }
+            //Operators past ASCII (which a program need not avoid) are shown as the character with that code
+            Abstract::Operator(o) => "Operator(".to_string() + &char::from(*o).to_string() + ")",
 
             Abstract::Loop(u) => "Loop(".to_string() + u.to_string().as_str() + ")",
         }
     }
 }
 
+//Everything code has defined as it runs, its aliases (jumps included) and the kill id the next loop will be given
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scope {
+    pub map: HashMap<String, Var>,
+    pub killidon: usize,
+}
+
+//Vars are sent between threads (see threads.rs), so this stops them from ever holding anything that can't be
+const _: fn() = || {
+    fn send<T: Send>() {}
+    send::<Var>();
+};
+
+//An error thrown by Rust code that QRT calls into, such as a host function, which QRT then throws as its own
+#[derive(Clone, Debug, PartialEq)]
+pub struct QrtError(pub String);
+
+impl From<&str> for QrtError {
+    fn from(msg: &str) -> QrtError {
+        QrtError(msg.to_string())
+    }
+}
+
+impl From<String> for QrtError {
+    fn from(msg: String) -> QrtError {
+        QrtError(msg)
+    }
+}
+
+impl fmt::Display for QrtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 //This rather complex type is given its own value here. It either has a variable, or a ton of debug info.
 pub type Evaluation = Result<
     Var,
@@ -0,0 +1,458 @@
+use std::ops::Range;
+
+//A syntax tree of QRT source, read the same way evaluate reads it but without running anything.
+//Every piece keeps the span of source it came from, and comments are kept, for tools that work on the source
+//itself (formatting, linting) rather than on its values.
+
+//Errors are the message and the position they were found at
+pub type SyntaxError = (&'static str, usize);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comment {
+    pub text: Vec<u8>, //Including its backslashes
+    pub span: Range<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Linear(f64),
+    Gestalt(Vec<u8>),
+    Void,
+    Input,
+    Random,
+    Set(Vec<Node>, Vec<Comment>), //Elements, and the comments after the last of them
+    Reference(Vec<u8>),
+    //Assignment (#), loops (~) and jump definitions (:), which take a plainly written name as their primary argument
+    Named(u8, Vec<u8>, Block),
+    Operation(u8, Box<Node>, Block),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub expression: Expression,
+    pub span: Range<usize>,
+    pub comments: Vec<Comment>, //Written before it, for primary arguments and Set elements
+}
+
+//A secondary argument, or the whole program, as a sequence of expressions
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub items: Vec<Item>,
+    pub comments: Vec<Comment>, //After the last item
+    pub span: Range<usize>,     //Including the brackets, if it has them
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    pub comments: Vec<Comment>,    //On the lines before it
+    pub node: Option<Node>,        //None for a terminator (;) on its own
+    pub terminated: bool,          //Followed by a terminator (;)
+    pub blank_before: bool,        //Separated from the item before it by an empty line
+    pub trailing: Option<Comment>, //On the same line, after it
+}
+
+impl Node {
+    //Calls the given function on this node and every node inside it, outermost first
+    pub fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a Node)) {
+        visit(self);
+
+        match &self.expression {
+            Expression::Set(elements, _) => elements.iter().for_each(|node| node.walk(visit)),
+            Expression::Named(_, _, block) => block.walk(visit),
+            Expression::Operation(_, primary, block) => {
+                primary.walk(visit);
+                block.walk(visit);
+            }
+            _ => (),
+        }
+    }
+
+    pub fn walk_scope<'a>(&'a self, visit: &mut impl FnMut(&'a Node)) {
+        visit(self);
+
+        match &self.expression {
+            Expression::Set(elements, _) => elements.iter().for_each(|node| node.walk_scope(visit)),
+            Expression::Named(b':', ..) => (),
+            Expression::Named(_, _, block) => block.walk_scope(visit),
+            Expression::Operation(_, primary, block) => {
+                primary.walk_scope(visit);
+                block.walk_scope(visit);
+            }
+            _ => (),
+        }
+    }
+
+    //The span of the operator and name of an assignment, loop or jump definition
+    pub fn head(&self) -> Range<usize> {
+        match &self.expression {
+            Expression::Named(_, name, _) => self.span.start..self.span.start + 1 + name.len(),
+            _ => self.span.start..self.span.start + 1,
+        }
+    }
+}
+
+impl Block {
+    pub fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a Node)) {
+        for item in &self.items {
+            if let Some(node) = &item.node {
+                node.walk(visit);
+            }
+        }
+    }
+
+    //Like walk, but without going into jump definitions, whose code runs with its own aliases
+    pub fn walk_scope<'a>(&'a self, visit: &mut impl FnMut(&'a Node)) {
+        for item in &self.items {
+            if let Some(node) = &item.node {
+                node.walk_scope(visit);
+            }
+        }
+    }
+
+    //The aliases this block defines for the code in its scope, with the span of each definition's name.
+    //Jumps are given with their trailing bang, the way they're referenced.
+    pub fn definitions(&self) -> Vec<(Vec<u8>, &Node)> {
+        let mut definitions = Vec::new();
+
+        self.walk_scope(&mut |node| match &node.expression {
+            Expression::Named(b'#' | b'~', name, _) if !name.ends_with(b"_") => {
+                definitions.push((name.clone(), node))
+            }
+            Expression::Named(b':', name, _) => {
+                definitions.push(([name.as_slice(), b"!"].concat(), node))
+            }
+            _ => (),
+        });
+
+        definitions
+    }
+}
+
+enum Trivia {
+    Newline,
+    Comment(Comment),
+}
+
+struct Parser<'a> {
+    program: &'a [u8],
+    on: usize,
+}
+
+//Reads a whole program
+pub fn parse(program: &[u8]) -> Result<Block, SyntaxError> {
+    Parser { program, on: 0 }.block(true)
+}
+
+impl Parser<'_> {
+    //Skips over whitespace, commas (evaluate skips them everywhere) and comments, giving back the newlines and comments
+    fn trivia(&mut self) -> Vec<Trivia> {
+        let mut trivia = Vec::new();
+
+        while let Some(c) = self.program.get(self.on) {
+            match c {
+                b'\n' => {
+                    trivia.push(Trivia::Newline);
+                    self.on += 1;
+                }
+
+                b' ' | b'\t' | b'\r' | b',' => self.on += 1,
+
+                //Comments run to the next backslash, or the end of the program
+                b'\\' => {
+                    let start = self.on;
+
+                    self.on = match self.program[start + 1..].iter().position(|c| *c == b'\\') {
+                        Some(end) => start + end + 2,
+                        None => self.program.len(),
+                    };
+
+                    let mut text = self.program[start..self.on].to_vec();
+                    if text.len() < 2 || !text.ends_with(b"\\") {
+                        text.push(b'\\');
+                    }
+
+                    trivia.push(Trivia::Comment(Comment {
+                        text,
+                        span: start..self.on,
+                    }));
+                }
+
+                _ => break,
+            }
+        }
+
+        trivia
+    }
+
+    //Only the comments out of some trivia, for places where newlines don't matter
+    fn comments(&mut self) -> Vec<Comment> {
+        self.trivia()
+            .into_iter()
+            .filter_map(|trivia| match trivia {
+                Trivia::Comment(comment) => Some(comment),
+                Trivia::Newline => None,
+            })
+            .collect()
+    }
+
+    //Reads items up to a closing bracket (left for the caller), or the end of the program at the top level
+    fn block(&mut self, top: bool) -> Result<Block, SyntaxError> {
+        let start = self.on;
+        let mut items: Vec<Item> = Vec::new();
+        let mut pending: Vec<Comment> = Vec::new();
+        let mut newlines = 0;
+
+        loop {
+            for trivia in self.trivia() {
+                match trivia {
+                    Trivia::Newline => newlines += 1,
+
+                    Trivia::Comment(comment) => match items.last_mut() {
+                        Some(last)
+                            if newlines == 0 && pending.is_empty() && last.trailing.is_none() =>
+                        {
+                            last.trailing = Some(comment)
+                        }
+                        _ => pending.push(comment),
+                    },
+                }
+            }
+
+            match self.program.get(self.on) {
+                None if top => break,
+                None => return Err(("No closing bracket found", self.on)),
+
+                Some(b'}') if top => return Err(("No opening bracket found", self.on)),
+                Some(b'}') => break,
+
+                Some(b';') => {
+                    self.on += 1;
+
+                    match items.last_mut() {
+                        Some(last)
+                            if newlines == 0
+                                && pending.is_empty()
+                                && last.node.is_some()
+                                && !last.terminated
+                                && last.trailing.is_none() =>
+                        {
+                            last.terminated = true
+                        }
+
+                        _ => items.push(Item {
+                            comments: std::mem::take(&mut pending),
+                            node: None,
+                            terminated: true,
+                            blank_before: newlines > 1 && !items.is_empty(),
+                            trailing: None,
+                        }),
+                    }
+                }
+
+                Some(_) => {
+                    let node = self.expression()?;
+
+                    items.push(Item {
+                        comments: std::mem::take(&mut pending),
+                        node: Some(node),
+                        terminated: false,
+                        blank_before: newlines > 1 && !items.is_empty(),
+                        trailing: None,
+                    });
+                }
+            }
+
+            newlines = 0;
+        }
+
+        Ok(Block {
+            items,
+            comments: pending,
+            span: start..self.on,
+        })
+    }
+
+    //Reads a secondary argument, starting on its opening bracket
+    fn secondary(&mut self) -> Result<Block, SyntaxError> {
+        let start = self.on;
+
+        if self.program.get(self.on) != Some(&b'{') {
+            return Err(("Operator given no secondary argument", self.on));
+        }
+        self.on += 1;
+
+        let mut block = self.block(false)?;
+
+        //block only stops at a closing bracket when it isn't at the top level
+        self.on += 1;
+        block.span = start..self.on;
+
+        Ok(block)
+    }
+
+    //Reads a plainly written name up to (not including) any of the given bytes, like evaluate does
+    fn name(&mut self, ends: &[u8]) -> Result<Vec<u8>, SyntaxError> {
+        let start = self.on;
+
+        while let Some(c) = self.program.get(self.on) {
+            if ends.contains(c) {
+                return Ok(self.program[start..self.on].to_vec());
+            }
+
+            self.on += 1;
+        }
+
+        Err(("Program ended in the middle of a name", self.on))
+    }
+
+    fn expression(&mut self) -> Result<Node, SyntaxError> {
+        let comments = self.comments();
+        let start = self.on;
+
+        let expression = match self.program.get(self.on) {
+            None => return Err(("Program ended where an expression was expected", self.on)),
+
+            Some(b'0'..=b'9') => {
+                while let Some(b'0'..=b'9' | b'.') = self.program.get(self.on) {
+                    self.on += 1;
+                }
+
+                match core::str::from_utf8(&self.program[start..self.on]).map(str::parse::<f64>) {
+                    Ok(Ok(l)) => Expression::Linear(l),
+                    _ => return Err(("Incorrect linear formatting", start)),
+                }
+            }
+
+            Some(b'"') => {
+                let mut gestalt = Vec::new();
+                let mut escape = false;
+
+                loop {
+                    self.on += 1;
+                    match self.program.get(self.on) {
+                        None => return Err(("No closing quote found for Gestalt literal", start)),
+                        Some(b'"') if !escape => break,
+                        Some(b'\\') if !escape => escape = true,
+                        Some(c) => {
+                            escape = false;
+                            gestalt.push(*c);
+                        }
+                    }
+                }
+                self.on += 1;
+
+                Expression::Gestalt(gestalt)
+            }
+
+            Some(b'_') => {
+                self.on += 1;
+                Expression::Void
+            }
+            Some(b'$') => {
+                self.on += 1;
+                Expression::Input
+            }
+            Some(b'%') => {
+                self.on += 1;
+                Expression::Random
+            }
+
+            Some(b'[') => {
+                self.on += 1;
+                let mut elements = Vec::new();
+
+                loop {
+                    let mut pending = self.comments();
+
+                    match self.program.get(self.on) {
+                        Some(b']') => {
+                            self.on += 1;
+                            break Expression::Set(elements, pending);
+                        }
+
+                        None => return Err(("No closing bracket found", start)),
+                        Some(b'}' | b';') => return Err(("Unexpected character in Set", self.on)),
+
+                        Some(_) => {
+                            let mut node = self.expression()?;
+                            pending.append(&mut node.comments);
+                            node.comments = pending;
+                            elements.push(node);
+                        }
+                    }
+                }
+            }
+
+            Some(b'(') => {
+                self.on += 1;
+                let name = self.name(b")")?;
+                self.on += 1;
+
+                Expression::Reference(name)
+            }
+
+            Some(operator @ (b'#' | b'~')) => {
+                self.on += 1;
+                let mut name = self.name(b"{!_")?;
+
+                match self.program[self.on] {
+                    b'!' => return Err(("Bangs (!) not allowed in variable names", self.on)),
+
+                    //The void literal stands in for the name, and anything can come between it and the bracket
+                    b'_' => {
+                        name.push(b'_');
+                        self.on += 1;
+                        self.comments();
+                    }
+
+                    _ => (),
+                }
+
+                Expression::Named(*operator, name, self.secondary()?)
+            }
+
+            Some(b':') => {
+                self.on += 1;
+                let name = self.name(b"{!")?;
+
+                if self.program[self.on] == b'!' {
+                    return Err(("Bangs (!) not allowed in function names", self.on));
+                }
+
+                Expression::Named(b':', name, self.secondary()?)
+            }
+
+            Some(b']') => {
+                return Err(("Likely: no opening bracket given for set literal", self.on))
+            }
+            Some(b'}' | b')' | b';') => return Err(("Unexpected character", self.on)),
+
+            Some(operator) => {
+                self.on += 1;
+
+                let mut primary = self.expression()?;
+                primary.comments.append(&mut self.comments());
+
+                Expression::Operation(*operator, Box::new(primary), self.secondary()?)
+            }
+        };
+
+        Ok(Node {
+            expression,
+            span: start..self.on,
+            comments,
+        })
+    }
+}
+
+//Turns a position in the source into a line and column, both counted from 0
+pub fn line_column(program: &[u8], on: usize) -> (usize, usize) {
+    let before = &program[..on.min(program.len())];
+    let line = before.iter().filter(|c| **c == b'\n').count();
+    let column = match before.iter().rposition(|c| *c == b'\n') {
+        Some(newline) => before.len() - newline - 1,
+        None => before.len(),
+    };
+
+    (line, column)
+}
@@ -0,0 +1,181 @@
+use super::{interpreter::CancelToken, structs::*};
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex, MutexGuard,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+//The threads evaluations have spawned, and the channels they send values along.
+//Everything spawned from an interpreter shares these with it, so any thread can join any other, or use any channel.
+//Threads and channels are known by ids, given out in order (never the same one twice) and kept in Linears.
+#[derive(Clone, Default)]
+pub struct Threads(Arc<Mutex<Shared>>);
+
+//A channel's receiver is shared, so a thread waiting on it doesn't stop others using the rest
+type Channel = (Sender<Var>, Arc<Mutex<Receiver<Var>>>);
+
+#[derive(Default)]
+struct Shared {
+    next: usize,
+    running: HashMap<usize, JoinHandle<Evaluation>>,
+    channels: HashMap<usize, Channel>,
+}
+
+//How long waiting threads go between checking whether they've been cancelled
+const CHECK_EVERY: Duration = Duration::from_millis(10);
+
+impl Threads {
+    //A thread poisons the lock only by panicking while holding it, which never leaves what's within half changed
+    fn shared(&self) -> MutexGuard<'_, Shared> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    //Runs the evaluation on a new thread, giving its id
+    pub fn spawn<F>(&self, evaluation: F) -> usize
+    where
+        F: FnOnce() -> Evaluation + Send + 'static,
+    {
+        let mut shared = self.shared();
+        let id = shared.next;
+        shared.next += 1;
+        shared.running.insert(id, thread::spawn(evaluation));
+        id
+    }
+
+    //Waits for the thread to finish, giving what its evaluation gave. Threads can only be joined once.
+    pub fn join(&self, id: usize) -> Result<Evaluation, String> {
+        let handle = match self.shared().running.remove(&id) {
+            Some(handle) => handle,
+            None => return Err("Thread not found".to_string()),
+        };
+
+        handle.join().map_err(|_| "Thread panicked".to_string())
+    }
+
+    //Makes a new channel, giving its id
+    pub fn channel(&self) -> usize {
+        let mut shared = self.shared();
+        let id = shared.next;
+        shared.next += 1;
+
+        let (sender, receiver) = channel();
+        shared
+            .channels
+            .insert(id, (sender, Arc::new(Mutex::new(receiver))));
+        id
+    }
+
+    pub fn send(&self, id: usize, var: Var) -> Result<(), String> {
+        match self.shared().channels.get(&id) {
+            //The channel keeps its own receiver, so sending can't fail
+            Some((sender, _)) => sender
+                .send(var)
+                .map_err(|_| "Channel not found".to_string()),
+            None => Err("Channel not found".to_string()),
+        }
+    }
+
+    //Waits for the next value sent along the channel, for at most the timeout if there is one (giving None if nothing
+    //was sent by then). Cancelling the token stops the wait with an error.
+    pub fn receive(
+        &self,
+        id: usize,
+        timeout: Option<Duration>,
+        cancel: &CancelToken,
+    ) -> Result<Option<Var>, String> {
+        let receiver = match self.shared().channels.get(&id) {
+            Some((_, receiver)) => receiver.clone(),
+            None => return Err("Channel not found".to_string()),
+        };
+        let receiver = receiver
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        //A deadline too far away to be kept is never reached
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+
+        loop {
+            if cancel.is_cancelled() {
+                return Err("Evaluation cancelled".to_string());
+            }
+
+            let wait = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return Ok(None);
+                    }
+                    left.min(CHECK_EVERY)
+                }
+                None => CHECK_EVERY,
+            };
+
+            match receiver.recv_timeout(wait) {
+                Ok(var) => return Ok(Some(var)),
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => return Err("Channel not found".to_string()),
+            }
+        }
+    }
+}
+
+//Evaluates every element on a pool of threads (one for each core), giving their values in the elements' order, or the
+//index and error of the first element that threw one.
+//Elements are taken in order, and none after one that threw are started, so every element before it has been
+//evaluated and the same one is always reported.
+pub fn parallel_map<T, F>(elements: &[T], evaluate: F) -> Result<Vec<Var>, (usize, String)>
+where
+    T: Sync,
+    F: Fn(&T) -> Evaluation + Sync,
+{
+    let workers = thread::available_parallelism()
+        .map(|cores| cores.get())
+        .unwrap_or(1)
+        .min(elements.len());
+
+    let next = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(usize::MAX);
+    let results: Vec<Mutex<Option<Evaluation>>> =
+        elements.iter().map(|_| Mutex::new(None)).collect();
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= elements.len() || index > failed.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let evaluation = evaluate(&elements[index]);
+                if evaluation.is_err() {
+                    failed.fetch_min(index, Ordering::Relaxed);
+                }
+                *results[index]
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(evaluation);
+            });
+        }
+    });
+
+    let mut values = Vec::with_capacity(elements.len());
+    for (index, result) in results.into_iter().enumerate() {
+        match result
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+        {
+            Some(Ok(value)) => values.push(value),
+            Some(Err((msg, ..))) => return Err((index, msg)),
+            //Only elements after the first that threw are skipped, so that one is always reached first
+            None => unreachable!(),
+        }
+    }
+    Ok(values)
+}
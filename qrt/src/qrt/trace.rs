@@ -0,0 +1,50 @@
+use super::{
+    interpreter::{Hook, Step},
+    structs::*,
+    syntax::line_column,
+};
+use std::io::Write;
+
+//Writes a line for every operator that gives a value, with where it was applied, its arguments, and what it gave.
+//Lines are indented by how many evaluations deep they happened, so the code run by a ! comes right before it.
+pub struct Tracer<W: Write> {
+    pub output: W,
+    program: Vec<u8>,
+}
+
+impl<W: Write> Tracer<W> {
+    //A tracer for the given program, writing to output
+    pub fn new(program: &[u8], output: W) -> Tracer<W> {
+        Tracer {
+            output,
+            program: program.to_vec(),
+        }
+    }
+}
+
+impl<W: Write> Hook for Tracer<W> {
+    fn applied(&mut self, step: &Step, operator: u8, a: &Var, b: &Var, result: &Var) {
+        let place = match step.origin {
+            Some(origin) => {
+                let (line, column) = line_column(&self.program, origin + step.on);
+                format!("{}:{}", line + 1, column + 1)
+            }
+            None => {
+                let (line, column) = line_column(step.program, step.on);
+                format!("(Gestalt code) {}:{}", line + 1, column + 1)
+            }
+        };
+
+        //Tracing is only for whoever is watching, so failing to write it isn't worth stopping the program over
+        let _ = writeln!(
+            self.output,
+            "{}{} {}{}{{{}}} = {}",
+            "  ".repeat(step.depth),
+            place,
+            operator as char,
+            a.to_qrt_literal(),
+            b.to_qrt_literal(),
+            result.to_qrt_literal()
+        );
+    }
+}
@@ -1,6 +1,28 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use crate::qrt::{evaluate::evaluate, structs::Var};
+    #[cfg(feature = "python")]
+    use crate::qrt::python::{self, from_python, to_python};
+    use crate::qrt::{
+        debug::Debugger,
+        evaluate::{evaluate, Task},
+        format::format,
+        golden::{self, diff, discover, run, Outcome},
+        interpreter::{Hook, Interpreter, Step},
+        io::BufferIo,
+        json::Objects,
+        lint::{lint, Warning},
+        lsp::{operators, serve, OPERATOR_TABLE},
+        profile::Profiler,
+        session::Session,
+        structs::{Abstract, QrtError, Var},
+        trace::Tracer,
+    };
+    use proptest::{collection::vec, prelude::*, sample::select};
+    #[cfg(feature = "python")]
+    use pyo3::{prelude::*, wrap_pyfunction};
+    use serde_json::json;
+    use std::{env, fs, path::Path, process::Command, task::Poll, thread, time::Duration};
 
     macro_rules! test {
         ( $( ($funcname:ident, $qrtcode:expr, $result:expr) ),*) => {
@@ -16,6 +38,40 @@ mod tests {
         };
     }
 
+    //Like test!, but for programs that should throw the given error message
+    macro_rules! test_error {
+        ( $( ($funcname:ident, $qrtcode:expr, $msg:expr) ),*) => {
+            $(
+                #[test]
+                fn $funcname() {
+                    match evaluate($qrtcode, &Var::Linear(42.0)) {
+                        Err((msg, ..)) => assert_eq!(msg, $msg),
+                        Ok(v) => panic!("Expected an error, got {}", v.represent()),
+                    }
+                }
+            )*
+        };
+    }
+
+    //Like test!, but attached to a console with the given input, checking what gets printed as well
+    macro_rules! test_console {
+        ( $( ($funcname:ident, $qrtcode:expr, $stdin:expr, $result:expr, $stdout:expr, $stderr:expr) ),*) => {
+            $(
+                #[test]
+                fn $funcname() {
+                    let mut interpreter = Interpreter::with_io(BufferIo::new($stdin));
+
+                    assert_eq!(
+                        interpreter.evaluate($qrtcode, &Var::Linear(42.0)),
+                        Ok($result)
+                    );
+                    assert_eq!(interpreter.io().output, $stdout.to_vec());
+                    assert_eq!(interpreter.io().errors, $stderr.to_vec());
+                }
+            )*
+        };
+    }
+
     test! {
         //RUDIMENTARIES
         (comments, b"\\hello world\\2", Var::Linear(2.0)),
@@ -81,10 +137,43 @@ mod tests {
             Var::Linear(1.0)
         ].to_vec())),
 
+        (xor, b"[X0{0}, X1{0}, X1{1}]", Var::Set([
+            Var::Linear(0.0),
+            Var::Linear(1.0),
+            Var::Linear(0.0)
+        ].to_vec())),
+
+        (not, b"[N0{_}, N1{_}, N_{_}]", Var::Set([
+            Var::Linear(1.0),
+            Var::Linear(0.0),
+            Var::Linear(1.0)
+        ].to_vec())),
+
+        (truthiness, b"[&\"a\"{[1]}, &\"\"{[1]}, |_{[]}]", Var::Set([
+            Var::Linear(1.0),
+            Var::Linear(0.0),
+            Var::Linear(0.0)
+        ].to_vec())),
+
         //COMPARISON
         (void_equality, b"[=_{_}, =1{_}]", Var::Set([
             Var::Linear(1.0),
-            Var::Void(())
+            Var::Linear(0.0)
+        ].to_vec())),
+        (cross_type_equality, b"[=1{\"1\"}, =[1]{1}]", Var::Set([
+            Var::Linear(0.0),
+            Var::Linear(0.0)
+        ].to_vec())),
+        (not_equal, b"[Q1{1}, Q1{2}, Q\"a\"{1}]", Var::Set([
+            Var::Linear(0.0),
+            Var::Linear(1.0),
+            Var::Linear(1.0)
+        ].to_vec())),
+        (ordering, b"[O\"apple\"{\"banana\"}, O[1, \"b\"]{[1, \"a\"]}, O[1, 2]{[1, 2]}, O[1]{[1, 2]}]", Var::Set([
+            Var::Linear(-1.0),
+            Var::Linear(1.0),
+            Var::Linear(0.0),
+            Var::Linear(-1.0)
         ].to_vec())),
         (linear_equality, b"[=1{1}, =0{1}]", Var::Set([
             Var::Linear(1.0),
@@ -116,8 +205,110 @@ mod tests {
         (gestalt_back_trim, b"<\"hello\"{1}", Var::Gestalt(b"ello".to_vec())),
         (set_back_trim, b"<[1,2,3]{1}", Var::Set([Var::Linear(2.0), Var::Linear(3.0)].to_vec())),
 
+        //GESTALT
+        (gestalt_escapes, br#"["\"a\\", "\\"]"#, Var::Set([
+            Var::Gestalt(br#""a\"#.to_vec()),
+            Var::Gestalt(br#"\"#.to_vec())
+        ].to_vec())),
+        //Code holding escapes can still be skipped over
+        (escapes_in_skipped_conditionals, br#"[?0{"\\"}1, ?0{"\""}2]"#, Var::Set([
+            Var::Linear(1.0),
+            Var::Linear(2.0)
+        ].to_vec())),
+        (escapes_in_jumps, br#":f{"\\";} :g{"\"";} [!(f!){_}, !(g!){_}]"#, Var::Set([
+            Var::Gestalt(br#"\"#.to_vec()),
+            Var::Gestalt(br#"""#.to_vec())
+        ].to_vec())),
+        (escapes_in_killed_loops, br#"~k{#_{"\\"} #_{"\""} (k)}7"#, Var::Linear(7.0)),
+        (gestalt_split, b"S\"a,b,,c\"{\",\"}", Var::Set([
+            Var::Gestalt(b"a".to_vec()),
+            Var::Gestalt(b"b".to_vec()),
+            Var::Gestalt(b"".to_vec()),
+            Var::Gestalt(b"c".to_vec())
+        ].to_vec())),
+        (set_join, b"J[\"a\", 1, \"c\"]{\", \"}", Var::Gestalt(b"a, 1, c".to_vec())),
+        (gestalt_find, b"[F\"hello\"{\"llo\"}, F\"hello\"{\"z\"}]", Var::Set([
+            Var::Linear(2.0),
+            Var::Linear(-1.0)
+        ].to_vec())),
+        (gestalt_replace, b"R\"a-b-c\"{[\"-\", \"--\"]}", Var::Gestalt(b"a--b--c".to_vec())),
+        (gestalt_case, b"[U\"Hello\"{_}, L\"Hello\"{_}]", Var::Set([
+            Var::Gestalt(b"HELLO".to_vec()),
+            Var::Gestalt(b"hello".to_vec())
+        ].to_vec())),
+        (gestalt_trim, b"T\"  hello \n\"{_}", Var::Gestalt(b"hello".to_vec())),
+        (gestalt_starts_ends_with, b"[H\"hello\"{\"he\"}, H\"hello\"{\"lo\"}, E\"hello\"{\"lo\"}]", Var::Set([
+            Var::Linear(1.0),
+            Var::Linear(0.0),
+            Var::Linear(1.0)
+        ].to_vec())),
+        (decimal_formatting, b"D3.14159{2}", Var::Gestalt(b"3.14".to_vec())),
+
+        //TYPES
+        (type_of, b"[Y_{_}, Y1{_}, Y\"a\"{_}, Y[]{_}]", Var::Set([
+            Var::Gestalt(b"Void".to_vec()),
+            Var::Gestalt(b"Linear".to_vec()),
+            Var::Gestalt(b"Gestalt".to_vec()),
+            Var::Gestalt(b"Set".to_vec())
+        ].to_vec())),
+        (gestalt_linear_conversion, b"[V\"2.5\"{\"Linear\"}, V2.5{\"Gestalt\"}]", Var::Set([
+            Var::Linear(2.5),
+            Var::Gestalt(b"2.5".to_vec())
+        ].to_vec())),
+        (gestalt_set_conversion, b"[V\"ab\"{\"Set\"}, V[\"a\", \"b\"]{\"Gestalt\"}]", Var::Set([
+            Var::Set([Var::Gestalt(b"a".to_vec()), Var::Gestalt(b"b".to_vec())].to_vec()),
+            Var::Gestalt(b"ab".to_vec())
+        ].to_vec())),
+        (safe_parsing, b"[P\"12\"{_}, P\"twelve\"{_}]", Var::Set([
+            Var::Linear(12.0),
+            Var::Void(())
+        ].to_vec())),
+
+        (byte_conversion, b"[B\"hi\"{_}, B[104, 105]{_}]", Var::Set([
+            Var::Set([Var::Linear(104.0), Var::Linear(105.0)].to_vec()),
+            Var::Gestalt(b"hi".to_vec())
+        ].to_vec())),
+
+        //JSON
+        (json_parsing, b"A\"{\\\"a\\\": [1, true, null], \\\"b\\\": \\\"text\\\"}\"{_}", Var::Set([
+            Var::Set([
+                Var::Gestalt(b"a".to_vec()),
+                Var::Set([Var::Linear(1.0), Var::Linear(1.0), Var::Void(())].to_vec())
+            ].to_vec()),
+            Var::Set([Var::Gestalt(b"b".to_vec()), Var::Gestalt(b"text".to_vec())].to_vec())
+        ].to_vec())),
+        (json_stringifying, b"[G[1.5, \"a\", _]{_}, G[[\"key\", 2]]{_}, G[[\"key\", 2]]{1}]", Var::Set([
+            Var::Gestalt(b"[1.5,\"a\",null]".to_vec()),
+            Var::Gestalt(b"[[\"key\",2]]".to_vec()),
+            Var::Gestalt(b"{\"key\":2}".to_vec())
+        ].to_vec())),
+
+        //SLICING
+        (gestalt_slice, b"[C\"hello\"{[1, 3]}, C\"hello\"{[-0{3}]}]", Var::Set([
+            Var::Gestalt(b"el".to_vec()),
+            Var::Gestalt(b"llo".to_vec())
+        ].to_vec())),
+        (set_slice, b"C[1,2,3,4]{[1, -0{1}]}", Var::Set([Var::Linear(2.0), Var::Linear(3.0)].to_vec())),
+        (set_write, b"W[1,2,3]{[-0{1}, \"c\"]}", Var::Set([
+            Var::Linear(1.0),
+            Var::Linear(2.0),
+            Var::Gestalt(b"c".to_vec())
+        ].to_vec())),
+        (gestalt_write, b"W\"hello\"{[0, \"j\"]}", Var::Gestalt(b"jello".to_vec())),
+        (set_insert, b"I[1,3]{[1, 2]}", Var::Set([Var::Linear(1.0), Var::Linear(2.0), Var::Linear(3.0)].to_vec())),
+        (gestalt_insert, b"I\"hello\"{[5, \"!\"]}", Var::Gestalt(b"hello!".to_vec())),
+        (negative_access, b"[`[1,2,3]{-0{1}}, `\"hello\"{-0{2}}, -[1,2,3]{-0{3}}]", Var::Set([
+            Var::Linear(3.0),
+            Var::Gestalt(b"l".to_vec()),
+            Var::Set([Var::Linear(2.0), Var::Linear(3.0)].to_vec())
+        ].to_vec())),
+
+        //TESTING
+        (passed_assertion, b"a[+1{1}, 2]{\"adds\"}", Var::void()),
+
         //MISCELLANEOUS
         (assignment_and_aliases, b"#me{2}(me)", Var::Linear(2.0)),
+        (discarding, b"2 [#_{1}]", Var::Set([].to_vec())),
 
         (evaluate_jump, b":plusone{+${1};}!(plusone!){1}", Var::Linear(2.0)),
         (evaluate_macro, b"!\"+${1}\"{1}", Var::Linear(2.0)),
@@ -140,6 +331,8 @@ mod tests {
             }
             (a)", Var::Linear(16.0)),
 
+        (empty_jump_definition, b":nothing{}(nothing!)", Var::Linear(9.0)),
+
         (modulus, b"`9{2}", Var::Linear(1.0)),
         (gestalt_access, b"`\"hello\"{3}", Var::Gestalt(b"l".to_vec())),
         (set_access, b"`[1,2,3]{1}", Var::Linear(2.0)),
@@ -180,4 +373,1378 @@ mod tests {
 
         ", Var::Linear(181.0))
     }
+
+    test_error! {
+        //SLICING
+        (out_of_bounds_removal, b"-[1,2,3]{3}", "Removal index out of bounds"),
+        (out_of_bounds_front_trim, b">\"hi\"{3}", "Cannot remove more characters than the Gestalt has"),
+        (out_of_bounds_back_trim, b"<[1]{2}", "Cannot remove more elements than the Set has"),
+        (out_of_bounds_slice, b"C[1,2]{[0, 3]}", "Slice range out of bounds"),
+        (backwards_slice, b"C[1,2]{[1, 0]}", "Slice start is after its end"),
+        (out_of_bounds_gestalt_access, b"`\"hi\"{2}", "Could not get index 2 from Gestalt"),
+
+        //TYPES
+        (invalid_byte, b"B[104, 256]{_}", "Bytes must be whole Linears from 0 to 255"),
+
+        //JSON
+        (invalid_json, b"A\"[1,\"{_}", "Invalid JSON: EOF while parsing a value at line 1 column 3"),
+
+        //FILES
+        (missing_file, b"@\"no/such/file.txt\"{_}", "Error in opening file"),
+        (unknown_file_operation, b"@\"file.txt\"{[\"shred\"]}", "Unknown file operation"),
+        (missing_directory_listing, b"@\"no/such/directory\"{[\"list\"]}", "Error in listing directory"),
+
+        //COMPARISON
+        (cross_type_ordering, b"O1{\"1\"}", "Cannot order values of different types"),
+        (nan_ordering, b"#nan{/0{0}} O(nan){(nan)}", "Cannot order NaN"),
+        (nested_nan_ordering, b"O[1, /0{0}]{[1, 2]}", "Cannot order NaN"),
+
+        //TESTING
+        (failed_assertion, b"a[+1{1}, 3]{\"adds\"}", "Assertion failed: adds (expected 3, got 2)"),
+        (failed_assertion_without_message, b"a[\"a\", [\"a\"]]{_}", "Assertion failed (expected [\"a\"], got \"a\")"),
+        (invalid_assertion, b"a[1]{\"one\"}", "Assertion needs a Set of the value given and the value expected"),
+
+        //HOST
+        (missing_host_function, b"h\"lookup\"{[1]}", "Host function not found"),
+        (invalid_host_arguments, b"h\"lookup\"{1}", "Invalid operand types"),
+
+        //CONCURRENCY
+        (missing_thread, b"j5{_}", "Thread not found"),
+        (missing_channel, b"t5{1}", "Channel not found"),
+        (spawning_nothing, b"s_{1}", "Invalid operand types"),
+        (mapping_a_linear, b"m\"1\"{5}", "Invalid operand types"),
+        (
+            mapping_errors,
+            b"m\"?=$ {2}{(x);} $\"{[1, 2, 3, 2]}",
+            "Variable does not exist (In element 1)"
+        ),
+        (negative_wait, b"#ch{c_{_}} w(ch){-0{1}}", "Invalid operand types"),
+
+        //PANICS
+        (unterminated_gestalt, b"+\"hello{1}", "No closing quote found for Gestalt literal"),
+        (unterminated_alias, b"(hello", "Program ended in the middle of a name"),
+        (unterminated_jump_definition, b":plusone{+${1};", "No closing bracket found"),
+        (out_of_bounds_jump, b"!1000{_}", "Jump out of bounds"),
+        (dead_loop_kill, b"~kill{(kill)}(kill)", "Killed loop is not running")
+    }
+
+    test_console! {
+        (print, b"p\"hello \"{_} p1.5{_} e\"oops\"{_} 2", b"", Var::Linear(2.0), b"hello 1.5", b"oops"),
+        (print_in_loop, b"
+            #a{0}
+            ~kill{
+                ?=(a){3}{
+                    (kill)
+                }
+                p(a){_}
+                #a{+(a){1}}
+            }", b"", Var::Void(()), b"012", b""),
+        (read_line, b"[l_{_}, l_{_}, l_{_}, l_{_}]", b"first\nsecond\r\n\n", Var::Set([
+            Var::Gestalt(b"first".to_vec()),
+            Var::Gestalt(b"second".to_vec()),
+            Var::Gestalt(b"".to_vec()),
+            Var::Void(())
+        ].to_vec()), b"", b""),
+        (read_all, b"#first{l_{_}} r_{_}", b"first\nsecond\nthird", Var::Gestalt(b"second\nthird".to_vec()), b"", b"")
+    }
+
+    //Gives a fresh temporary directory for a test touching the disk, as a path that can go in a Gestalt literal
+    fn temp_dir(name: &str) -> String {
+        let dir = env::temp_dir().join(format!("qrt_{}_{}", name, std::process::id()));
+
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        dir.to_string_lossy().replace('\\', "\\\\")
+    }
+
+    #[test]
+    fn file_operations() {
+        let dir = temp_dir("file_operations");
+
+        let program = format!(
+            "
+            #dir{{\"{dir}\"}}
+            #file{{+(dir){{\"/notes.txt\"}}}}
+            #_{{@(file){{\"hello\"}}}}
+            #_{{@(file){{[\"append\", \" world\"]}}}}
+            #_{{@+(dir){{\"/sub\"}}{{[\"mkdir\"]}}}}
+            [
+                @(file){{_}},
+                @(dir){{[\"list\"]}},
+                `@(file){{[\"metadata\"]}}{{0}},
+                @(file){{[\"delete\"]}},
+                @(file){{[\"exists\"]}}
+            ]"
+        );
+
+        assert_eq!(
+            evaluate(program.as_bytes(), &Var::void()),
+            Ok(Var::Set(vec![
+                Var::Gestalt(b"hello world".to_vec()),
+                Var::Set(vec![
+                    Var::Gestalt(b"notes.txt".to_vec()),
+                    Var::Gestalt(b"sub".to_vec())
+                ]),
+                Var::Linear(11.0),
+                Var::Void(()),
+                Var::Linear(0.0)
+            ]))
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn binary_file_operations() {
+        let dir = temp_dir("binary_file_operations");
+        let file = format!("{}/data.bin", dir);
+
+        let program = format!(
+            "
+            #_{{@\"{file}\"{{[\"writebytes\", B[0, 159, 255]{{_}}]}}}}
+            B@\"{file}\"{{[\"readbytes\"]}}{{_}}"
+        );
+
+        assert_eq!(
+            evaluate(program.as_bytes(), &Var::void()),
+            Ok(Var::Set(vec![
+                Var::Linear(0.0),
+                Var::Linear(159.0),
+                Var::Linear(255.0)
+            ]))
+        );
+
+        match evaluate(format!("@\"{file}\"{{_}}").as_bytes(), &Var::void()) {
+            Err((msg, ..)) => assert_eq!(msg, "File is not valid text, try reading its bytes"),
+            Ok(v) => panic!("Expected an error, got {}", v.represent()),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let json = r#"{"name":"qrt","tags":["a","b"],"nested":{"empty":[],"none":null}}"#;
+        let var: Var = serde_json::from_str(json).unwrap();
+
+        assert_eq!(serde_json::to_string(&Objects(&var)).unwrap(), json);
+        assert_eq!(
+            serde_json::to_string(&var).unwrap(),
+            r#"[["name","qrt"],["tags",["a","b"]],["nested",[["empty",[]],["none",null]]]]"#
+        );
+        assert!(serde_json::to_string(&Var::Kill(0)).is_err());
+    }
+
+    #[test]
+    fn literal_printing() {
+        let var = Var::Set(vec![
+            Var::Void(()),
+            Var::Linear(-2.5),
+            Var::Linear(f64::NEG_INFINITY),
+            Var::Linear(f64::NAN),
+            Var::Gestalt(br#"say "hi" \"#.to_vec()),
+            Var::Gestalt(vec![104, 255]),
+            Var::Set(vec![]),
+            Var::Kill(3),
+        ]);
+
+        assert_eq!(
+            var.to_string(),
+            r#"[_, -0{2.5}, -0{/1{0}}, /0{0}, "say \"hi\" \\", B[104, 255]{_}, [], \KillID(3)\_]"#
+        );
+        assert_eq!(
+            var.represent(),
+            "[Void, -2.5, -inf, NaN, \"say \"hi\" \\\", \"h\u{FFFD}\", [], KillID(3)]"
+        );
+        assert_eq!(Abstract::Operator(0xC3).represent(), "Operator(\u{C3})");
+
+        assert_eq!(
+            Var::from_qrt_literal(b"[1,\n  \\comment\\ \"a\" ]"),
+            Ok(Var::Set(vec![
+                Var::Linear(1.0),
+                Var::Gestalt(b"a".to_vec())
+            ]))
+        );
+        assert_eq!(Var::from_qrt_literal(b"+1{2}"), Err("Invalid literal"));
+        assert_eq!(
+            Var::from_qrt_literal(b"[1, 2"),
+            Err("No closing bracket found")
+        );
+    }
+
+    #[test]
+    fn formatting() {
+        let program = b"#a{0}  \\counter\\
+~kill{?=(a){10}{(kill)}#a{+(a){1}}}
+
+
+[(a),[1,2],\"{\\\"}\"];";
+
+        assert_eq!(
+            String::from_utf8(format(program).unwrap()).unwrap(),
+            "#a{0} \\counter\\
+~kill{
+    ?=(a){10}{
+        (kill)
+    }
+    #a{+(a){1}}
+}
+
+[(a), [1, 2], \"{\\\"}\"];
+"
+        );
+        assert_eq!(format(b"+1{2"), Err(("No closing bracket found", 4)));
+    }
+
+    //Formatting shouldn't change what a program does, and formatting twice should change nothing more
+    #[test]
+    fn formatting_preserves_programs() {
+        let programs: [&[u8]; 3] = [
+            b"#primes{[2]}#checking{3}~main{?=^(primes){_}{50}{(main)}#isprime{1}#checkingon{0}
+            ~checker{?=(checkingon){^(primes){_}}{(checker)}\\ divisible? \\?=`(checking){`(primes){(checkingon)}}{0}{
+            #isprime{0}(checker)}#checkingon{+(checkingon){1}}}?(isprime){#primes{+(primes){(checking)}}}
+            #checking{+(checking){1}}}`(primes){-^(primes){_}{1}};",
+            b":double{*${2};}[!(double!){4}, #_{1}, \\set comment\\ [\"a\\\\\", _]]",
+            b"\t#x{[1,\n2]}\n\t\\trailing comment",
+        ];
+
+        for program in programs {
+            let formatted = format(program).unwrap();
+
+            assert_eq!(format(&formatted).unwrap(), formatted);
+            assert_eq!(
+                evaluate(&formatted, &Var::void()),
+                evaluate(program, &Var::void())
+            );
+        }
+    }
+
+    #[test]
+    fn linting() {
+        let program = b"#a{0}
+~kill{?=(a){10}{(kill)}#a{+(a){1}}(a)}
+~forever{#b{1}}
+:inc{+${1};}
+!(inc){(undefined)}
++\"a\"{[1]}
+?\"yes\"{#c{1}}
+K1{2}
+(a);
+(b)";
+
+        let warnings: Vec<(&str, std::ops::Range<usize>)> = lint(program)
+            .unwrap()
+            .into_iter()
+            .map(|warning| (warning.code, warning.span))
+            .collect();
+
+        assert_eq!(
+            warnings,
+            vec![
+                ("W002", 40..43),
+                ("W003", 45..53),
+                ("W004", 75..80),
+                ("W001", 81..92),
+                ("W006", 94..95),
+                ("W006", 104..105),
+                ("W006", 118..119),
+                ("W005", 129..132),
+            ]
+        );
+
+        assert_eq!(
+            lint(b"#x{1}:f{#y{(x)}(y);}[(x), !(f!){_}, ^\"ab\"{_}]"),
+            Ok(vec![Warning {
+                code: "W001",
+                message: "Alias x is never defined".to_string(),
+                span: 11..14
+            }])
+        );
+
+        //Sending along a channel gives nothing, so it can be done within a loop
+        assert_eq!(lint(b"#ch{c_{_}} ~loop{ t(ch){1} (loop) }"), Ok(vec![]));
+
+        //Test jumps can use the jumps defined outside of any jump, just as they can when they're run
+        let program = b":double{*${2};}
+:test_double{
+    a[!(double!){2}, 4]{\"doubles 2\"}
+    a[!(double!){0}, 0]{\"doubles 0\"};
+}
+:check{a[!(double!){2}, 4]{_};}";
+        assert_eq!(
+            lint(program)
+                .unwrap()
+                .into_iter()
+                .map(|warning| (warning.code, warning.span))
+                .collect::<Vec<_>>(),
+            vec![("W001", 117..126)]
+        );
+    }
+
+    #[test]
+    fn language_server() {
+        let uri = "file:///main.qrt";
+        let text = "#count{0}\n:inc{+${1};}\n#count{!(inc!){(count)}}\n(cont)";
+        let position =
+            |line: usize, character: usize| json!({ "line": line, "character": character });
+
+        let requests = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+                "textDocument": { "uri": uri, "languageId": "qrt", "version": 1, "text": text }
+            }}),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/definition", "params": {
+                "textDocument": { "uri": uri }, "position": position(2, 10)
+            }}),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "textDocument/hover", "params": {
+                "textDocument": { "uri": uri }, "position": position(1, 5)
+            }}),
+            json!({ "jsonrpc": "2.0", "id": 4, "method": "textDocument/completion", "params": {
+                "textDocument": { "uri": uri }, "position": position(3, 1)
+            }}),
+            json!({ "jsonrpc": "2.0", "id": 5, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ];
+
+        let mut input = Vec::new();
+        for request in requests {
+            let content = request.to_string();
+            input.extend(format!("Content-Length: {}\r\n\r\n{}", content.len(), content).bytes());
+        }
+
+        let mut output = Vec::new();
+        serve(input.as_slice(), &mut output).unwrap();
+
+        let responses: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .split("Content-Length: ")
+            .skip(1)
+            .map(|message| serde_json::from_str(message.split_once("\r\n\r\n").unwrap().1).unwrap())
+            .collect();
+
+        assert_eq!(responses.len(), 6);
+        assert_eq!(
+            responses[0]["result"]["capabilities"]["hoverProvider"],
+            true
+        );
+
+        let diagnostics = &responses[1]["params"]["diagnostics"];
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+        assert_eq!(diagnostics[0]["code"], "W001");
+        assert_eq!(
+            diagnostics[0]["range"],
+            json!({ "start": position(3, 0), "end": position(3, 6) })
+        );
+
+        assert_eq!(
+            responses[2]["result"]["range"],
+            json!({ "start": position(1, 0), "end": position(1, 4) })
+        );
+        assert_eq!(
+            responses[3]["result"]["contents"]["value"],
+            "`+` addition, linear coercion, gestalt coercion, gestalt concatenation, linear appending, gestalt \
+appending, set appending
+
+- Linear-Linear (addition): returns a + b
+- Linear-Gestalt (linear coercion): coerces b to a number, then returns a + b
+- Gestalt-Linear (gestalt coercion): coerces b to a string, then returns b concatenated to a
+- Gestalt-Gestalt (gestalt concatenation): returns b concatentated to a
+- Set-Linear (linear appending): returns a with b added to the end
+- Set-Gestalt (gestalt appending): returns a with b added to the end
+- Set-Set (set appending): returns a with b added to the end as a subset"
+        );
+
+        //Every operator's docs come from their table, with lines that carry on joined to the one they carry on from
+        let documented: Vec<u8> = operators().iter().map(|(operator, _)| *operator).collect();
+        for operator in b"#?:!~@hpelrsjctwm+-*/^&|XNQO=<>SJFRULTHEDYVPBAGC`WIa".iter() {
+            assert!(
+                documented.contains(operator),
+                "{} isn't documented",
+                *operator as char
+            );
+        }
+
+        //They're copied from the README, which has to say just the same
+        let handbook = include_str!("../../README.txt");
+        let mut blocks: Vec<String> = Vec::new();
+        for line in OPERATOR_TABLE
+            .lines()
+            .skip_while(|line| !line.starts_with("    "))
+        {
+            if !line.starts_with("        ") {
+                blocks.push(String::new());
+            }
+            if let Some(block) = blocks.last_mut() {
+                *block += &format!("{}\n", line);
+            }
+        }
+        assert_eq!(blocks.len(), operators().len());
+        for block in blocks {
+            assert!(
+                handbook.contains(&block),
+                "The README doesn't say:\n{}",
+                block
+            );
+        }
+
+        let (_, conversion) = operators().iter().find(|(o, _)| *o == b'V').unwrap();
+        assert_eq!(conversion.len(), 1);
+        assert!(
+            conversion[0].ends_with("Sets of Gestalts convert back to Gestalts by concatenation.")
+        );
+
+        let completions = responses[4]["result"].as_array().unwrap();
+        assert_eq!(completions[0]["label"], "count");
+        assert_eq!(completions[0]["insertText"], "count");
+        assert_eq!(completions[1]["label"], "inc!");
+        assert_eq!(completions.len(), 2);
+
+        assert_eq!(
+            responses[5],
+            json!({ "jsonrpc": "2.0", "id": 5, "result": null })
+        );
+    }
+
+    //Records where every step was taken, within the whole program
+    struct Recorder(Vec<(Option<usize>, usize)>);
+
+    impl Hook for Recorder {
+        fn step(&mut self, step: &Step) -> Result<(), String> {
+            self.0
+                .push((step.origin.map(|origin| origin + step.on), step.depth));
+
+            match step.map.get("stop") {
+                Some(_) => Err("Stopped".to_string()),
+                None => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn hooks() {
+        let mut interpreter =
+            Interpreter::with_io(BufferIo::default()).with_hook(Recorder(Vec::new()));
+
+        assert_eq!(
+            interpreter.evaluate(b":f{+$ {1};} !(f!){\"!1{_}\"}", &Var::void()),
+            Ok(Var::Gestalt(b"!1{_}1".to_vec()))
+        );
+        assert_eq!(
+            interpreter.hook.0,
+            [
+                (Some(0), 0),
+                (Some(12), 0),
+                (Some(13), 0),
+                (Some(17), 0),
+                (Some(18), 0),
+                (Some(25), 0),
+                (Some(3), 1),
+                (Some(4), 1),
+                (Some(6), 1),
+                (Some(7), 1),
+                (Some(8), 1),
+                (Some(9), 1),
+            ]
+            .to_vec()
+        );
+
+        //Gestalts evaluated as code aren't anywhere in the program, and an error from the hook stops evaluation
+        interpreter.hook.0.clear();
+        match interpreter.evaluate(b"!\"#stop{1} 2\"{_} 3", &Var::void()) {
+            Err((msg, ..)) => assert_eq!(msg, "Stopped(In function evaluated at 15)"),
+            Ok(v) => panic!("Expected an error, got {}", v.represent()),
+        }
+        assert_eq!(interpreter.hook.0.last(), Some(&(None, 1)));
+    }
+
+    #[test]
+    fn host_functions() {
+        let mut interpreter = Interpreter::with_io(BufferIo::default());
+        let users = ["ada", "grace"];
+
+        interpreter.register("user", move |arguments| match arguments {
+            [Var::Linear(id)] => match users.get(*id as usize) {
+                Some(name) => Ok(Var::Gestalt(name.as_bytes().to_vec())),
+                None => Err(QrtError(format!("No user {}", id))),
+            },
+            _ => Err("user needs an id".into()),
+        });
+        interpreter.register("count", |arguments| Ok(Var::Linear(arguments.len() as f64)));
+
+        //Host functions can be called from jumps and Gestalts evaluated as code as well
+        assert_eq!(
+            interpreter.evaluate(
+                b":name{h\"user\"{[$]};} [!(name!){1}, h\"count\"{[1, _, \"\"]}, !\"h\\\"count\\\"{[]}\"{_}]",
+                &Var::void()
+            ),
+            Ok(Var::Set(vec![
+                Var::Gestalt(b"grace".to_vec()),
+                Var::Linear(3.0),
+                Var::Linear(0.0)
+            ]))
+        );
+
+        //Errors are thrown from where the host function was called
+        match interpreter.evaluate(b"#a{1}\nh\"user\"{[2]}", &Var::void()) {
+            Err((msg, on, line, ..)) => assert_eq!(
+                (msg.as_str(), on, line),
+                ("No user 2 (In host function user)", 17, 1)
+            ),
+            Ok(v) => panic!("Expected an error, got {}", v.represent()),
+        }
+        match interpreter.evaluate(b"h\"user\"{[\"ada\"]}", &Var::void()) {
+            Err((msg, ..)) => assert_eq!(msg, "user needs an id (In host function user)"),
+            Ok(v) => panic!("Expected an error, got {}", v.represent()),
+        }
+    }
+
+    #[test]
+    fn sessions() {
+        let mut session = Session::with_interpreter(Interpreter::with_io(BufferIo::default()));
+
+        assert_eq!(
+            session.run(b"#rate{2} :scale{*$ {2};} ~stop{(stop)}", &Var::void()),
+            Ok(Var::void())
+        );
+        assert_eq!(session.get("rate"), Some(&Var::Linear(2.0)));
+
+        //Later code carries on with everything defined, and the host can change it in between
+        session.set("rate", Var::Linear(3.0));
+        assert_eq!(
+            session.run(b"+(rate){!(scale!){$}}", &Var::Linear(5.0)),
+            Ok(Var::Linear(13.0))
+        );
+        assert_eq!(
+            session.call("scale", &Var::Linear(4.0)),
+            Ok(Var::Linear(8.0))
+        );
+
+        //Loops started later are given their own kill ids, so old kill aliases can't stop them
+        match session.run(b"~loop{(stop)}", &Var::void()) {
+            Err((msg, ..)) => assert_eq!(msg, "Killed loop is not running"),
+            Ok(v) => panic!("Expected an error, got {}", v.represent()),
+        }
+
+        //Errors are within all of the code run, and whatever was defined before them is kept
+        match session.run(b"#late{1}\n(missing)", &Var::void()) {
+            Err((msg, on, line, ..)) => {
+                assert_eq!((msg.as_str(), line), ("Variable does not exist", 4));
+                assert_eq!(&session.source()[on - 9..on], b"(missing)");
+            }
+            Ok(v) => panic!("Expected an error, got {}", v.represent()),
+        }
+        assert_eq!(session.get("late"), Some(&Var::Linear(1.0)));
+
+        match session.call("missing", &Var::void()) {
+            Err((msg, ..)) => assert_eq!(msg, "Jump not found"),
+            Ok(v) => panic!("Expected an error, got {}", v.represent()),
+        }
+    }
+
+    #[test]
+    fn tasks() {
+        let mut interpreter = Interpreter::with_io(BufferIo::default());
+
+        //Steps within jumps count too, and the value comes once the last step has run
+        let mut task = Task::new(b":double{*${2};} p1{_} !(double!){+1{2}}", &Var::void());
+        assert_eq!(task.run(&mut interpreter, 6), Poll::Pending);
+        assert_eq!(interpreter.io().output, b"1");
+        assert_eq!(task.run(&mut interpreter, 10), Poll::Pending);
+        assert_eq!(task.run(&mut interpreter, 4), Poll::Pending);
+        assert_eq!(
+            task.run(&mut interpreter, 1),
+            Poll::Ready(Ok(Var::Linear(6.0)))
+        );
+        assert!(matches!(task.run(&mut interpreter, 1), Poll::Ready(Err(_))));
+
+        //Errors still say every evaluation they were within
+        let mut task = Task::new(b"!\"!\\\"(x)\\\"{_}\"{_}", &Var::void());
+        match task.run(&mut interpreter, usize::MAX) {
+            Poll::Ready(Err((msg, ..))) => assert_eq!(
+                msg,
+                "Variable does not exist(In function evaluated at 8)(In function evaluated at 16)"
+            ),
+            other => panic!("Expected an error, got {:?}", other),
+        }
+        assert_eq!(task.thrown_at(), Some(16));
+
+        //Where in the program an error was thrown is kept, since its own position is within the jump
+        let mut task = Task::new(b":f{+1{\"a\"};} !(f!){_}", &Var::void());
+        assert!(matches!(task.finish(&mut interpreter), Err((_, 6, ..))));
+        assert_eq!(task.thrown_at(), Some(9));
+
+        //Jumps no longer recurse within Rust, so they can go far deeper than the stack could
+        assert_eq!(
+            interpreter.evaluate(b":down{?$ {!0{-$ {1}};} 0;} !(down!){100000}", &Var::void()),
+            Ok(Var::Linear(0.0))
+        );
+    }
+
+    #[test]
+    fn snapshots() {
+        let program = b":twice{*$ {2};} #raw{B[255]{_}} #total{0} #i{0}
+            ~done{
+                ?=(i){4}{(done)}
+                #total{+(total){!(twice!){(i)}}}
+                p(i){_}
+                #i{+(i){1}}
+            }
+            [(total), (raw), !\"+$ {1};\"{(i)}]";
+        let expected = Var::Set(vec![
+            Var::Linear(12.0),
+            Var::Gestalt(vec![255]),
+            Var::Linear(5.0),
+        ]);
+
+        //Wherever it's paused, a restored task carries on just the same, and snapshots the same way
+        for steps in 0.. {
+            let mut interpreter = Interpreter::with_io(BufferIo::default());
+            let mut task = Task::new(program, &Var::void());
+
+            if let Poll::Ready(result) = task.run(&mut interpreter, steps) {
+                assert_eq!(result, Ok(expected));
+                break;
+            }
+
+            let snapshot = task.snapshot();
+            let mut restored = Task::restore(&snapshot).unwrap();
+            assert_eq!(restored.snapshot(), snapshot);
+
+            assert_eq!(restored.finish(&mut interpreter), Ok(expected.clone()));
+            assert_eq!(interpreter.io().output, b"0123");
+        }
+
+        assert_eq!(
+            Task::restore(b"[\"QRT snapshot\", 2, [], [], [0, []]]").err(),
+            Some("Unsupported snapshot version 2".to_string())
+        );
+        assert_eq!(
+            Task::restore(
+                b"[\"QRT snapshot\", 1, [\"1\"], [[0, 2, _, [0, []], [], 0, _, 0, _]], [0, []]]"
+            )
+            .err(),
+            Some("Invalid snapshot (frame's start)".to_string())
+        );
+
+        //Nothing out of range is restored, since evaluation would count up from it or read the code there
+        for (frames, error) in [
+            (r#"[[0, 0, _, [0, []], [], 0, _, N, _]], [0, []]"#, "number"),
+            (r#"[[0, 0, _, [N, []], [], 0, _, 0, _]], [0, []]"#, "number"),
+            (r#"[[0, 0, _, [0, []], [], 0, _, 0, _]], [N, []]"#, "number"),
+            (
+                r#"[[0, 0, _, [0, [["k", ["kill", N]]]], [], 0, _, 0, _]], [0, []]"#,
+                "number",
+            ),
+            (
+                r#"[[0, 0, _, [0, []], [["loop", N]], 0, _, 0, _]], [0, []]"#,
+                "number",
+            ),
+            (r#"[[0, 0, _, [0, []], [], 0, N, 0, _]], [0, []]"#, "number"),
+            (
+                r#"[[0, 0, _, [0, []], [], 2, _, 0, _]], [0, []]"#,
+                "frame's position",
+            ),
+        ] {
+            let frames = frames.replace('N', "100000000000000000000000000000");
+            assert_eq!(
+                Task::restore(format!(r#"["QRT snapshot", 1, ["1"], {}]"#, frames).as_bytes())
+                    .err(),
+                Some(format!("Invalid snapshot ({})", error))
+            );
+        }
+        assert!(Task::restore(b"[1, 2").is_err());
+    }
+
+    #[test]
+    fn cancellation() {
+        let mut interpreter = Interpreter::with_io(BufferIo::default());
+        let token = interpreter.cancel_token();
+
+        let cancelling = token.clone();
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            cancelling.cancel();
+        });
+
+        match interpreter.evaluate(b":spin{~forever{#_{1}};} !(spin!){_}", &Var::void()) {
+            Err((msg, ..)) => {
+                assert_eq!(msg, "Evaluation cancelled \n(In function evaluated at 34)")
+            }
+            Ok(v) => panic!("Expected an error, got {}", v.represent()),
+        }
+        canceller.join().unwrap();
+
+        assert!(interpreter.evaluate(b"1", &Var::void()).is_err());
+        token.reset();
+        assert_eq!(
+            interpreter.evaluate(b"1", &Var::void()),
+            Ok(Var::Linear(1.0))
+        );
+    }
+
+    #[test]
+    fn threads() {
+        let mut interpreter = Interpreter::with_io(BufferIo::default());
+        interpreter.register("double", |arguments: &[Var]| match arguments {
+            [Var::Linear(x)] => Ok(Var::Linear(x * 2.0)),
+            _ => Err(QrtError::from("double needs a Linear")),
+        });
+
+        //Threads run jumps and macros, share host functions and channels, and give back what they return
+        let program = b":square{*$ {$};}
+            #messages{c_{_}}
+            #first{s(square!){3}}
+            #second{s\"t$ {h\\\"double\\\"{[21]}}\"{(messages)}}
+            #_{j(second){_}}
+            [j(first){_}, w(messages){_}, w(messages){0}]";
+        assert_eq!(
+            interpreter.evaluate(program, &Var::void()),
+            Ok(Var::Set(vec![
+                Var::Linear(9.0),
+                Var::Linear(42.0),
+                Var::void()
+            ]))
+        );
+
+        //Errors are thrown where the thread was joined, which can only be done once
+        match interpreter.evaluate(b"#t{s\"(x)\"{_}} j(t){_}", &Var::void()) {
+            Err((msg, on, ..)) => {
+                assert_eq!(msg, "Variable does not exist (In thread 3)");
+                assert_eq!(on, 20);
+            }
+            Ok(v) => panic!("Expected an error, got {}", v.represent()),
+        }
+        match interpreter.evaluate(b"#t{s\"1\"{_}} #_{j(t){_}} j(t){_}", &Var::void()) {
+            Err((msg, ..)) => assert_eq!(msg, "Thread not found"),
+            Ok(v) => panic!("Expected an error, got {}", v.represent()),
+        }
+
+        //Timeouts too long to be measured wait forever, rather than giving up at once
+        assert_eq!(
+            interpreter.evaluate(
+                b"#c{c_{_}} #_{t(c){1}} #_{t(c){2}} [w(c){/1{0}}, w(c){^10{300}}]",
+                &Var::void()
+            ),
+            Ok(Var::Set(vec![Var::Linear(1.0), Var::Linear(2.0)]))
+        );
+
+        //Spawned evaluations share the interpreter's console, rather than using the real one
+        let mut interpreter = Interpreter::with_io(BufferIo::new(b"first\nsecond\n"));
+        assert_eq!(
+            interpreter.evaluate(
+                b"#t{s\"pl_{_}{_} e\\\"!\\\"{_}\"{_}} #_{j(t){_}} m\"p$ {_} $\"{[1]} l_{_}",
+                &Var::void()
+            ),
+            Ok(Var::Gestalt(b"second".to_vec()))
+        );
+        assert_eq!(interpreter.io().output, b"first1");
+        assert_eq!(interpreter.io().errors, b"!");
+
+        //Mapping gives every value in order, whichever thread evaluated it
+        let numbers = Var::Set((0..100).map(|n| Var::Linear(n as f64)).collect());
+        assert_eq!(
+            interpreter.evaluate(b":square{*$ {$};} m(square!){$}", &numbers),
+            Ok(Var::Set(
+                (0..100).map(|n| Var::Linear((n * n) as f64)).collect()
+            ))
+        );
+        assert_eq!(
+            interpreter.evaluate(b"m\"1\"{[]}", &Var::void()),
+            Ok(Var::Set(vec![]))
+        );
+
+        //Cancelling the interpreter stops its threads, and anything waiting on them
+        let token = interpreter.cancel_token();
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            token.cancel();
+        });
+        match interpreter.evaluate(
+            b"#_{s\"~forever{#_{1}}\"{_}} #ch{c_{_}} w(ch){_}",
+            &Var::void(),
+        ) {
+            Err((msg, ..)) => assert_eq!(msg, "Evaluation cancelled"),
+            Ok(v) => panic!("Expected an error, got {}", v.represent()),
+        }
+        canceller.join().unwrap();
+    }
+
+    //Builds ffi/test.c against the shared library and runs it. It needs a C compiler (cc), and the shared library
+    //built first (cargo build, since cargo test doesn't build it), so it's only run when asked for.
+    #[cfg(unix)]
+    #[test]
+    fn c_api() {
+        if Command::new("cc").arg("--version").output().is_err() {
+            eprintln!("Skipping the C API test, as there's no C compiler (cc) to build it with");
+            return;
+        }
+
+        //The shared library is built by the test, in its own target directory so it doesn't wait on this one
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let target = env::current_exe()
+            .unwrap()
+            .ancestors()
+            .nth(3)
+            .unwrap()
+            .join("c_api");
+        let built = Command::new(env!("CARGO"))
+            .args(["build", "--lib", "--offline", "--manifest-path"])
+            .arg(manifest.join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(&target)
+            .output()
+            .unwrap();
+        assert!(
+            built.status.success(),
+            "{}",
+            String::from_utf8_lossy(&built.stderr)
+        );
+
+        let library = target.join("debug");
+        let ffi = manifest.join("ffi");
+        let test = Path::new(&temp_dir("c_api")).join("test");
+
+        let built = Command::new("cc")
+            .arg(ffi.join("test.c"))
+            .arg("-I")
+            .arg(&ffi)
+            .arg("-L")
+            .arg(&library)
+            .arg(format!("-Wl,-rpath,{}", library.display()))
+            .arg("-lqrt")
+            .arg("-o")
+            .arg(&test)
+            .output()
+            .unwrap();
+        assert!(
+            built.status.success(),
+            "{}",
+            String::from_utf8_lossy(&built.stderr)
+        );
+
+        //Cargo points the library path at its own target directory, which would come before the rpath
+        let ran = Command::new(&test)
+            .env("LD_LIBRARY_PATH", &library)
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&ran.stdout), "");
+        assert!(ran.status.success());
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn python_module() {
+        Python::initialize();
+
+        Python::attach(|py| {
+            let var = Var::Set(vec![
+                Var::Linear(1.5),
+                Var::Gestalt(b"text".to_vec()),
+                Var::Gestalt(vec![255]),
+                Var::Set(vec![Var::void()]),
+            ]);
+            let object = to_python(py, &var).unwrap();
+            assert_eq!(
+                object.repr().unwrap().to_string(),
+                "[1.5, 'text', b'\\xff', [None]]"
+            );
+            assert_eq!(from_python(&object).unwrap(), var);
+            assert!(to_python(py, &Var::Kill(0)).is_err());
+
+            //Ints, bools and tuples become Linears and Sets as well
+            let evaluate = wrap_pyfunction!(python::evaluate, py).unwrap();
+            let result = evaluate.call1(("*$ {[2]}", (1, true))).unwrap();
+            assert_eq!(
+                from_python(&result).unwrap(),
+                Var::Set(vec![Var::Linear(1.0), Var::Linear(1.0), Var::Linear(2.0)])
+            );
+
+            //Errors thrown within a jump are placed where they are in the source, not the jump
+            for (source, line, column) in [
+                ("#a{1}\n+(a){\"b\"}", 2, 9),
+                ("\n\n:f{\n+1{\"a\"};}\n!(f!){0}", 4, 7),
+            ] {
+                let error = evaluate.call1((source,)).unwrap_err();
+                let value = error.value(py);
+                assert!(error.is_instance_of::<python::QrtError>(py));
+                assert!(value
+                    .getattr("message")
+                    .unwrap()
+                    .to_string()
+                    .starts_with("Could not coerce Gestalt to Linear"));
+                assert_eq!(
+                    value.getattr("line").unwrap().extract::<usize>().unwrap(),
+                    line
+                );
+                assert_eq!(
+                    value.getattr("column").unwrap().extract::<usize>().unwrap(),
+                    column
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn debugging() {
+        let program = b"#count{0}\n:inc{+${1};}\n~loop{\n    #count{!(inc!){(count)}}\n    ?>(count){2}{(loop)}\n}\n";
+        let commands = b"b 5\nc\nmap\nw +(count){100}\nc\nd 5\nb 2\nc\np $\ns\ns\nstack\no\nq\n";
+
+        let mut interpreter = Interpreter::with_io(BufferIo::default())
+            .with_hook(Debugger::new(program, BufferIo::new(commands)));
+
+        match interpreter.evaluate(program, &Var::void()) {
+            Err((msg, ..)) => assert_eq!(msg, "Stopped by the debugger"),
+            Ok(v) => panic!(
+                "Expected the debugger to stop the program, got {}",
+                v.represent()
+            ),
+        }
+
+        assert_eq!(
+            String::from_utf8(interpreter.hook.io.output).unwrap(),
+            "Line 1, character 1 (depth 0):
+    1 | #count{0}
+      | ^
+> Breakpoint set on line 5
+> Line 5, character 5 (depth 0):
+    5 |     ?>(count){2}{(loop)}
+      |     ^
+> count: 1
+inc!: 15
+loop: KillID(0)
+> 1: +(count){100} = 101
+> Line 5, character 5 (depth 0):
+    5 |     ?>(count){2}{(loop)}
+      |     ^
+1: +(count){100} = 102
+> Breakpoint removed from line 5
+> Breakpoint set on line 2
+> Line 2, character 6 (depth 1):
+    2 | :inc{+${1};}
+      |      ^
+1: +(count){100} = Error: Variable does not exist
+> 2
+> Line 2, character 7 (depth 1):
+    2 | :inc{+${1};}
+      |       ^
+1: +(count){100} = Error: Variable does not exist
+> Line 2, character 8 (depth 1):
+    2 | :inc{+${1};}
+      |        ^
+1: +(count){100} = Error: Variable does not exist
+> Operator(+)
+Var(2)
+> Line 4, character 28 (depth 0):
+    4 |     #count{!(inc!){(count)}}
+      |                            ^
+1: +(count){100} = 102
+> "
+        );
+    }
+
+    #[test]
+    fn debugging_non_ascii() {
+        //Programs need not be valid UTF-8, so neither are the operators on the stack
+        let program = "+é1{2}".as_bytes();
+        let mut interpreter = Interpreter::with_io(BufferIo::default())
+            .with_hook(Debugger::new(program, BufferIo::new(b"s\ns\nstack\nq\n")));
+
+        match interpreter.evaluate(program, &Var::void()) {
+            Err((msg, ..)) => assert_eq!(msg, "Stopped by the debugger"),
+            Ok(v) => panic!(
+                "Expected the debugger to stop the program, got {}",
+                v.represent()
+            ),
+        }
+
+        assert_eq!(
+            String::from_utf8(interpreter.hook.io.output).unwrap(),
+            "Line 1, character 1 (depth 0):
+    1 | +é1{2}
+      | ^
+> Line 1, character 2 (depth 0):
+    1 | +é1{2}
+      |  ^
+> Line 1, character 3 (depth 0):
+    1 | +é1{2}
+      |   ^
+> Operator(+)
+Operator(\u{C3})
+> "
+        );
+    }
+
+    #[test]
+    fn tracing() {
+        let program = b"#a{+1{2}}\n:f{*${2};}\n!(f!){(a)}\n!\"-$ {1}\"{5}\n";
+        let mut interpreter =
+            Interpreter::with_io(BufferIo::default()).with_hook(Tracer::new(program, Vec::new()));
+
+        assert_eq!(
+            interpreter.evaluate(program, &Var::void()),
+            Ok(Var::Linear(4.0))
+        );
+        assert_eq!(
+            String::from_utf8(interpreter.hook.output).unwrap(),
+            "1:8 +1{2} = 3
+  2:8 *3{2} = 6
+3:10 !13{3} = 6
+  (Gestalt code) 1:6 -5{1} = 4
+4:12 !\"-$ {1}\"{5} = 4
+"
+        );
+
+        //Reading from the console gives values too
+        let program = b"[l_{_}, r_{_}]";
+        let mut interpreter = Interpreter::with_io(BufferIo::new(b"first\nrest"))
+            .with_hook(Tracer::new(program, Vec::new()));
+        assert!(interpreter.evaluate(program, &Var::void()).is_ok());
+        assert_eq!(
+            String::from_utf8(interpreter.hook.output).unwrap(),
+            "1:6 l_{_} = \"first\"\n1:13 r_{_} = \"rest\"\n"
+        );
+
+        //And so do channels, sending giving a Void
+        let program = b"#ch{c_{_}} t(ch){1} w(ch){_}";
+        let mut interpreter =
+            Interpreter::with_io(BufferIo::default()).with_hook(Tracer::new(program, Vec::new()));
+        assert_eq!(
+            interpreter.evaluate(program, &Var::void()),
+            Ok(Var::Linear(1.0))
+        );
+        assert_eq!(
+            String::from_utf8(interpreter.hook.output).unwrap(),
+            "1:9 c_{_} = 0\n1:19 t0{1} = _\n1:28 w0{_} = 1\n"
+        );
+    }
+
+    #[test]
+    fn profiling() {
+        let program = b"#count{0}\n:inc{+${1};}\n~loop{\n    #count{!(inc!){(count)}}\n    ?>(count){2}{(loop)}\n}\n";
+        let mut interpreter =
+            Interpreter::with_io(BufferIo::default()).with_hook(Profiler::new(program));
+
+        assert_eq!(interpreter.evaluate(program, &Var::void()), Ok(Var::void()));
+
+        let profiler = &mut interpreter.hook;
+        profiler.finish();
+
+        let steps: Vec<(Option<usize>, usize)> = profiler
+            .lines
+            .iter()
+            .map(|(line, cost)| (*line, cost.count))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                (Some(1), 4),
+                (Some(2), 19),
+                (Some(3), 2),
+                (Some(4), 24),
+                (Some(5), 22),
+                (Some(6), 2)
+            ]
+            .to_vec()
+        );
+
+        assert_eq!(profiler.jumps.keys().collect::<Vec<_>>(), [":inc (line 2)"]);
+        assert_eq!(profiler.jumps[":inc (line 2)"].count, 3);
+        assert_eq!(
+            profiler.loops.keys().collect::<Vec<_>>(),
+            ["~loop (line 3)"]
+        );
+        assert_eq!(profiler.loops["~loop (line 3)"].count, 1);
+        assert_eq!(
+            profiler.stacks.keys().collect::<Vec<_>>(),
+            [
+                "main",
+                "main;~loop (line 3)",
+                "main;~loop (line 3);:inc (line 2)"
+            ]
+        );
+
+        //Jumps and loops include everything run within them
+        let total: Duration = profiler.lines.values().map(|cost| cost.time).sum();
+        assert_eq!(profiler.stacks.values().sum::<Duration>(), total);
+        assert!(profiler.loops["~loop (line 3)"].time >= profiler.jumps[":inc (line 2)"].time);
+
+        let table = profiler.table();
+        assert!(table.starts_with("Total time: "));
+        assert!(table.contains("\nJumps:\n") && table.contains("  :inc (line 2)\n"));
+
+        for line in profiler.folded().lines() {
+            let (stack, time) = line.rsplit_once(' ').unwrap();
+            assert!(stack.starts_with("main") && time.parse::<u128>().is_ok());
+        }
+    }
+
+    #[test]
+    fn golden_files() {
+        let dir = env::temp_dir().join(format!("qrt_golden_files_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("lib")).unwrap();
+
+        let files: [(&str, &[u8]); 7] = [
+            ("double.qrt", b"\\input: 5\\\n\\result: 10\\\n*${2}\n"),
+            ("hello.qrt", b"p\"hello\"{_} p\"world\"{_}"),
+            ("hello.out", b"helloworld"),
+            ("wrong.qrt", b"\\result: 3\\\np\"b\"{_} +1{1}"),
+            ("wrong.out", b"a"),
+            ("lib/read.qrt", b"#l{l_{_}} #_{l_{_}} (nope)"),
+            ("lib/read.in", b"line one\nline two"),
+        ];
+        for (name, contents) in files {
+            fs::write(dir.join(name), contents).unwrap();
+        }
+
+        let paths = discover(&dir).unwrap();
+        assert_eq!(
+            paths,
+            ["double.qrt", "hello.qrt", "lib/read.qrt", "wrong.qrt"]
+                .map(|name| dir.join(name))
+                .to_vec()
+        );
+
+        let outcomes: Vec<Outcome> = paths.iter().map(|path| run(path, false).unwrap()).collect();
+        assert_eq!(
+            outcomes,
+            [
+                Outcome::Passed,
+                Outcome::Passed,
+                Outcome::Failed(vec!["Unexpected error: Variable does not exist".to_string()]),
+                Outcome::Failed(vec![
+                    "Result differs: expected 3, got 2".to_string(),
+                    "Output differs:\n- a\n+ b\n".to_string()
+                ]),
+            ]
+            .to_vec()
+        );
+
+        //Updating rewrites whatever didn't match, after which everything passes
+        let outcomes: Vec<Outcome> = paths.iter().map(|path| run(path, true).unwrap()).collect();
+        assert_eq!(
+            outcomes,
+            [
+                Outcome::Passed,
+                Outcome::Passed,
+                Outcome::Updated,
+                Outcome::Updated
+            ]
+            .to_vec()
+        );
+        assert_eq!(
+            fs::read(dir.join("lib/read.err")).unwrap(),
+            b"Variable does not exist\n"
+        );
+        assert_eq!(
+            fs::read(dir.join("wrong.qrt")).unwrap(),
+            b"\\result: 2\\\np\"b\"{_} +1{1}"
+        );
+        assert_eq!(fs::read(dir.join("wrong.out")).unwrap(), b"b");
+        assert!(paths
+            .iter()
+            .all(|path| run(path, false).unwrap() == Outcome::Passed));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_jumps() {
+        let path = Path::new("math.qrt");
+        let program = b":double{*${2};}
+:test_double{
+    a[!(double!){2}, 4]{\"doubles\"};
+}
+:outer{
+    :test_inner{;}
+;}
+~loop{
+    :test_wrong{a[!(double!){1.5}, 4]{_};}
+    (loop)
+}
+:test_unreturned{a[1, 1]{_}}
+:test_unreturned_wrong{a[1, 2]{_}}";
+
+        let tests = golden::test_jumps(program);
+        assert_eq!(
+            tests
+                .iter()
+                .map(|test| test.name.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "test_double",
+                "test_wrong",
+                "test_unreturned",
+                "test_unreturned_wrong"
+            ]
+        );
+        assert_eq!(tests[0].span, 16..28);
+
+        assert_eq!(golden::run_test(path, program, &tests[0]), Outcome::Passed);
+        assert_eq!(
+            golden::run_test(path, program, &tests[1]),
+            Outcome::Failed(vec![
+                "math.qrt:9:40: Assertion failed (expected 4, got 3)".to_string()
+            ])
+        );
+
+        //Tests that don't return with ; pass once they reach their closing bracket
+        assert_eq!(golden::run_test(path, program, &tests[2]), Outcome::Passed);
+        assert_eq!(
+            golden::run_test(path, program, &tests[3]),
+            Outcome::Failed(vec![
+                "math.qrt:13:33: Assertion failed (expected 2, got 1)".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn diffing() {
+        assert_eq!(diff("a\nb\nc\n", "a\nc\nd\n"), "  a\n- b\n  c\n+ d\n");
+        assert_eq!(
+            diff("a\n", "a"),
+            "  a\n(only the expected text ends with a newline)\n"
+        );
+    }
+
+    //Any var that has a literal, NaN aside since it never equals itself
+    fn literal_vars() -> impl Strategy<Value = Var> {
+        let leaf = prop_oneof![
+            Just(Var::void()),
+            any::<f64>()
+                .prop_filter("NaN never equals itself", |l| !l.is_nan())
+                .prop_map(Var::Linear),
+            any::<String>().prop_map(|s| Var::Gestalt(s.into_bytes())),
+            vec(any::<u8>(), 0..8).prop_map(Var::Gestalt),
+        ];
+
+        leaf.prop_recursive(4, 32, 6, |inner| vec(inner, 0..6).prop_map(Var::Set))
+    }
+
+    //FUZZING
+
+    //Threads are kept out of generated programs, since spawned ones (s, m) can spawn themselves forever, and joining
+    //(j) can wait forever. Waiting on a channel (w) is only stopped by Bounded when it could wait longer than
+    //FUZZ_WAIT. Everything else is bounded by Bounded, and the console is swapped for a buffer.
+    const TAMED: &[u8] = b"smj";
+
+    //Bytes that make up QRT syntax, so the generated programs get further than the first character
+    const ALPHABET: &[u8] =
+        b"0123456789.\"\\{}[](),;#:?_$%+-*/^&|=<>`~!@SJFRULTHEDYVPBAGCWINXQOpelrabctw \n";
+
+    //How many steps a generated program can take, how big its values can get, and how many seconds it can wait on a
+    //channel, before it's stopped
+    const FUZZ_STEPS: usize = 10000;
+    const FUZZ_SIZE: usize = 1 << 16;
+    const FUZZ_WAIT: f64 = 0.01;
+
+    //Stops generated programs that would never halt or grow without end, and any file access outside of the sandbox
+    struct Bounded {
+        steps: usize,
+        sandbox: String,
+    }
+
+    impl Hook for Bounded {
+        fn step(&mut self, step: &Step) -> Result<(), String> {
+            self.steps += 1;
+            if self.steps > FUZZ_STEPS {
+                return Err("Too many steps".to_string());
+            }
+
+            let size: usize = step
+                .stack
+                .iter()
+                .map(|element| match element {
+                    Abstract::Var(var) => size(var),
+                    _ => 1,
+                })
+                .chain(step.map.values().map(size))
+                .sum();
+            if size > FUZZ_SIZE {
+                return Err("Values too big".to_string());
+            }
+
+            //File access happens at the closing bracket, with the path just below the secondary argument
+            if step.program.get(step.on) == Some(&b'}')
+                && matches!(step.stack.get(2), Some(Abstract::Operator(b'@')))
+            {
+                let inside = match step.stack.get(1) {
+                    Some(Abstract::Var(Var::Gestalt(path))) => {
+                        path.starts_with(self.sandbox.as_bytes())
+                            && !path.windows(2).any(|pair| pair == b"..")
+                    }
+                    _ => true,
+                };
+                if !inside {
+                    return Err("Outside of the sandbox".to_string());
+                }
+            }
+
+            //Waiting happens at the closing bracket too, with the timeout as the secondary argument
+            if step.program.get(step.on) == Some(&b'}')
+                && matches!(step.stack.get(2), Some(Abstract::Operator(b'w')))
+            {
+                let bounded = match step.stack.front() {
+                    Some(Abstract::Var(Var::Linear(seconds))) => *seconds <= FUZZ_WAIT,
+                    _ => false,
+                };
+                if !bounded {
+                    return Err("Waits too long".to_string());
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    fn size(var: &Var) -> usize {
+        match var {
+            Var::Gestalt(g) => g.len(),
+            Var::Set(set) => set.iter().map(size).sum::<usize>() + 1,
+            _ => 1,
+        }
+    }
+
+    //Evaluates a generated program, with every path written in it (as @"path") moved into the sandbox
+    fn fuzz(sandbox: &str, program: &[u8], stdin: &[u8], input: &Var) {
+        let sandbox = format!("{}/", sandbox);
+        let mut moved = Vec::new();
+        for (i, c) in program.iter().enumerate() {
+            moved.push(*c);
+            if *c == b'"' && i > 0 && program[i - 1] == b'@' {
+                moved.extend_from_slice(sandbox.as_bytes());
+            }
+        }
+
+        let _ = Interpreter::with_io(BufferIo::new(stdin))
+            .with_hook(Bounded { steps: 0, sandbox })
+            .evaluate(&moved, input);
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_bytes_never_panic(program in vec(any::<u8>(), 0..128)) {
+            let program: Vec<u8> = program
+                .into_iter()
+                .map(|c| if TAMED.contains(&c) { b' ' } else { c })
+                .collect();
+
+            fuzz(&temp_dir("fuzz_bytes"), &program, b"input", &Var::void());
+        }
+
+        #[test]
+        fn literals_round_trip(var in literal_vars()) {
+            let literal = var.to_qrt_literal();
+
+            prop_assert_eq!(Var::from_qrt_literal(literal.as_bytes()), Ok(var.clone()));
+            prop_assert_eq!(evaluate(literal.as_bytes(), &Var::void()), Ok(var));
+        }
+
+        #[test]
+        fn arbitrary_syntax_never_panics(program in vec(select(ALPHABET), 0..64)) {
+            let input = Var::Set(vec![Var::Linear(1.0), Var::Gestalt(b"a".to_vec())]);
+            fuzz(&temp_dir("fuzz_syntax"), &program, b"input\nlines", &input);
+        }
+    }
 }